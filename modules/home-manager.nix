@@ -49,10 +49,16 @@
                 description = "Number of days after which files are automatically archived.";
               };
 
-              auto_archive_min_scans = lib.mkOption {
-                type = lib.types.ints.positive;
-                default = 24;
-                description = "Minimum number of times a file must be notified before auto-archiving.";
+              auto_archive_min_notified_days = lib.mkOption {
+                type = lib.types.ints.unsigned;
+                default = 3;
+                description = "Minimum number of different days a file must be notified on before auto-archiving.";
+              };
+
+              auto_archive_min_notified_span_days = lib.mkOption {
+                type = lib.types.ints.unsigned;
+                default = 3;
+                description = "Minimum number of days between the first and the last of those notifications.";
               };
 
               notification = lib.mkOption {
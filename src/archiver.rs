@@ -1,18 +1,58 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use crate::config::Config;
+use crate::bundle;
+use crate::config::{Compression, Config, View, ViewItem};
+use crate::epitaph::{Epitaph, EPITAPH_EXTENSION};
+use crate::index::GraveyardIndex;
+use crate::journal::{Journal, Operation};
+use crate::lock::Lock;
 use crate::scanner::StaleItem;
 use crate::state::NotificationState;
+use crate::store::ContentStore;
+use crate::utils::{
+    copy_dir_all, create_relative_symlink, format_size, item_hash, item_size, move_item,
+    numbered_name, remove_dir_with_permissions, remove_empty_parents,
+};
 
 pub struct Archiver {
     config: Config,
 }
 
-#[derive(Debug)]
-pub struct ArchivedItem {}
+/// Everything the archiver did with a single inbox item.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedItem {
+    pub name: String,
+    pub inbox: String,
+    /// Where the item lived in its inbox before being archived
+    pub original_path: PathBuf,
+    pub is_directory: bool,
+    /// Size in bytes (recursive for folders)
+    pub size: u64,
+    pub created: DateTime<Utc>,
+    pub modified: DateTime<Utc>,
+    pub archived: DateTime<Utc>,
+    /// Full copies of the item in the Graveyard, the first one being the moved original
+    pub stored_paths: Vec<PathBuf>,
+    /// Symlink views pointing at a stored copy
+    pub symlink_paths: Vec<PathBuf>,
+    /// Epitaph files written next to each stored copy
+    pub epitaph_paths: Vec<PathBuf>,
+    /// Epitaph symlinks next to each symlink view
+    pub epitaph_symlink_paths: Vec<PathBuf>,
+    /// Format of the bundle the item was packed into, if it was compressed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Compression>,
+    /// SHA-256 of the stored item, also recorded in its epitaph
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    /// Bytes not written because identical content was already in the store
+    #[serde(default)]
+    pub bytes_saved: u64,
+}
 
 impl Archiver {
     pub fn new(config: Config) -> Self {
@@ -24,42 +64,106 @@ impl Archiver {
         item: &StaleItem,
         note: Option<&str>,
     ) -> Result<ArchivedItem> {
+        let _lock = Lock::graveyard(&self.config)?;
         let now = Utc::now();
 
         let created_time = self.get_creation_time(&item.path)?;
         let modified_time = item.last_modified;
         let archived_time = now;
-
-        // Find all subdirs that need original files
-        let mut original_subdirs = self.find_original_subdirs()?;
-
-        // Set proper times for each subdir
-        for (subdir_name, time) in original_subdirs.iter_mut() {
-            if self.config.path_format.created_subdir.get_name() == Some(subdir_name) {
-                *time = created_time;
-            } else if self.config.path_format.modified_subdir.get_name() == Some(subdir_name) {
-                *time = modified_time;
-            } else if self.config.path_format.archived_subdir.get_name() == Some(subdir_name) {
-                *time = archived_time;
-            }
-        }
+        let size = item_size(&item.path)?;
+
+        // Everything the views file the item by
+        let view_item = ViewItem {
+            name: item.name.clone(),
+            is_directory: item.is_directory,
+            inbox: Some(item.inbox.clone()),
+            hostname: self.config.get_hostname(),
+            created: created_time,
+            modified: modified_time,
+            archived: archived_time,
+        };
+
+        // Find all views that need original files
+        let original_views = self.find_original_views()?;
 
         let mut primary_path = None;
         let mut created_paths = std::collections::HashMap::new();
+        let mut stored_paths = Vec::new();
+
+        // Compressed items are stored as a single bundle file
+        let compression = item.policy.compress;
+        let bundle_name = compression
+            .map(|compression| format!("{}.{}", item.name, compression.extension()))
+            .unwrap_or_else(|| item.name.clone());
+
+        // One name for the item in every view, so its copies, links and
+        // epitaphs all match, even if only some views already had that name
+        let stored_name = self.free_name(&view_item, &bundle_name);
+
+        let mut members = Vec::new();
+        let mut checksum = None;
+        let store = ContentStore::new(&self.config).filter(|_| !self.config.dry_run);
+        let mut bytes_saved = 0;
 
         // Create original files in all required subdirs
-        for (i, (subdir_name, time)) in original_subdirs.iter().enumerate() {
-            let target_path = self.create_path_for_subdir(subdir_name, &item.name, *time)?;
+        for (i, view) in original_views.iter().enumerate() {
+            let subdir_name = view.subdir.get_name().unwrap().to_string();
+            let target_path = self.get_path_in_view(view, &view_item, &stored_name);
+
+            if self.config.dry_run {
+                if i == 0 && compression.is_some() {
+                    self.report(format!(
+                        "🔍 Would compress '{}' -> {}",
+                        item.path.display(),
+                        target_path.display()
+                    ));
+                } else if i == 0 {
+                    self.report(format!(
+                        "🔍 Would move '{}' -> {}",
+                        item.path.display(),
+                        target_path.display()
+                    ));
+                } else {
+                    self.report(format!(
+                        "🔍 Would copy '{}' -> {}",
+                        item.name,
+                        target_path.display()
+                    ));
+                }
+                created_paths.insert(subdir_name, target_path.clone());
+                stored_paths.push(target_path);
+                continue;
+            }
+
             self.ensure_directory_exists(target_path.parent().unwrap())?;
 
             if i == 0 {
-                // Move the original file to the first location
-                self.move_item(&item.path, &target_path)
-                    .context("Failed to move item to graveyard")?;
+                if let Some(compression) = compression {
+                    // Bundle the item, then drop it from the inbox like a move would
+                    members = bundle::pack(&item.path, &target_path, compression)?;
+                    // Make sure the bundle reads back before dropping the original
+                    bundle::contents(&target_path, compression)?;
+                    if item.path.is_dir() {
+                        remove_dir_with_permissions(&item.path)?;
+                    } else {
+                        fs::remove_file(&item.path).context("Failed to remove compressed item")?;
+                    }
+                } else {
+                    // Move the original file to the first location
+                    move_item(&item.path, &target_path)
+                        .context("Failed to move item to graveyard")?;
+                }
+                checksum = Some(item_hash(&target_path)?);
+                // The content goes into the store once, and other views link to it
+                if let Some(store) = &store {
+                    bytes_saved += store.absorb(&target_path)?;
+                }
                 primary_path = Some(target_path.clone());
+            } else if let Some(store) = &store {
+                bytes_saved += store.link_copy(primary_path.as_ref().unwrap(), &target_path)?;
             } else {
                 // Copy to additional locations
-                if item.path.is_dir() {
+                if primary_path.as_ref().unwrap().is_dir() {
                     copy_dir_all(primary_path.as_ref().unwrap(), &target_path)?;
                 } else {
                     fs::copy(primary_path.as_ref().unwrap(), &target_path)
@@ -67,46 +171,193 @@ impl Archiver {
                 }
             }
 
-            created_paths.insert(subdir_name.clone(), target_path.clone());
-            println!("🪦 Stored '{}' in: {}", item.name, target_path.display());
+            created_paths.insert(subdir_name, target_path.clone());
+            stored_paths.push(target_path.clone());
+            self.report(format!(
+                "🪦 Stored '{}' in: {}",
+                item.name,
+                target_path.display()
+            ));
         }
 
         // Create symlinks for any remaining enabled subdirs
-        self.create_remaining_symlinks(
-            &item.name,
+        let symlink_paths =
+            self.create_remaining_symlinks(&stored_name, &created_paths, &view_item)?;
+
+        // Save epitaphs - create them in all relevant subdirs following same logic as files.
+        // Every item gets one so the graveyard remembers which inbox it came from.
+        let (epitaph_paths, epitaph_symlink_paths) = self.save_epitaphs_with_logic(
+            item,
+            &stored_name,
             &created_paths,
-            created_time,
-            modified_time,
-            archived_time,
+            note.unwrap_or(""),
+            checksum.as_deref(),
+            &view_item,
         )?;
 
-        // Save epitaphs if provided - create them in all relevant subdirs following same logic as files
-        if let Some(note_text) = note {
-            self.save_epitaphs_with_logic(
-                &item.name,
-                &created_paths,
-                note_text,
-                &created_time,
-                &modified_time,
-                &archived_time,
-            )?;
-        }
-
         // Reset notification count since file was archived
+        let _state_lock = Lock::state(&self.config)?;
         let mut state = NotificationState::load().unwrap_or_default();
-        state.reset_notification_count(&item.name);
-        state.save()?;
+        let tracked = state.tracked(&item.inbox, &item.identity).cloned();
+        state.reset_notification_count(&item.inbox, &item.identity);
+        state.save_unless_dry_run(
+            &self.config,
+            &format!("reset notification count for '{}'", item.name),
+        )?;
 
-        println!(
+        let archived_item = ArchivedItem {
+            name: item.name.clone(),
+            inbox: item.inbox.clone(),
+            original_path: item.path.clone(),
+            is_directory: item.is_directory,
+            size,
+            created: created_time,
+            modified: modified_time,
+            archived: archived_time,
+            stored_paths,
+            symlink_paths,
+            epitaph_paths,
+            epitaph_symlink_paths,
+            compression: item.policy.compress,
+            checksum,
+            bytes_saved,
+        };
+
+        if self.config.dry_run {
+            self.report(format!(
+                "🔍 Would archive '{}' to {} locations",
+                item.name,
+                original_views.len()
+            ));
+            return Ok(archived_item);
+        }
+
+        let index_entries = archived_item
+            .stored_paths
+            .iter()
+            .map(|path| {
+                GraveyardIndex::entry_for(&self.config, &archived_item, path, note, &members)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        GraveyardIndex::append(&self.config, &index_entries)?;
+
+        Journal::record(
+            &self.config,
+            Operation::Archive {
+                item: archived_item.clone(),
+                notification_count: tracked.as_ref().map_or(0, |tracked| tracked.count),
+                first_seen: tracked.as_ref().and_then(|tracked| tracked.first_seen),
+                notified: tracked.map(|tracked| tracked.notified).unwrap_or_default(),
+            },
+        );
+
+        self.report(format!(
             "✅ Archived '{}' to {} locations",
             item.name,
-            original_subdirs.len()
-        );
+            original_views.len()
+        ));
         if note.is_some() {
-            println!("📝 Epitaph saved with the archived item");
+            self.report("📝 Epitaph saved with the archived item");
+        }
+        if bytes_saved > 0 {
+            self.report(format!(
+                "♻️  Deduplicated identical content, saved {}",
+                format_size(bytes_saved)
+            ));
+        }
+
+        Ok(archived_item)
+    }
+
+    /// Revert an archive: move the item back to its inbox path and remove every
+    /// copy, symlink view and epitaph the archiver created for it.
+    pub fn unarchive(&self, item: &ArchivedItem) -> Result<()> {
+        let _lock = Lock::graveyard(&self.config)?;
+        if item.original_path.symlink_metadata().is_ok() {
+            anyhow::bail!(
+                "Cannot restore '{}': {} already exists",
+                item.name,
+                item.original_path.display()
+            );
+        }
+
+        // Any surviving full copy will do if the primary one went missing
+        let Some(source) = item.stored_paths.iter().find(|path| path.exists()) else {
+            anyhow::bail!("No stored copy of '{}' is left in the Graveyard", item.name);
+        };
+
+        if self.config.dry_run {
+            let verb = if item.compression.is_some() {
+                "unpack"
+            } else {
+                "move"
+            };
+            self.report(format!(
+                "🔍 Would {verb} '{}' -> {}",
+                source.display(),
+                item.original_path.display()
+            ));
+        } else {
+            if let Some(parent) = item.original_path.parent() {
+                self.ensure_directory_exists(parent)?;
+            }
+            if let Some(compression) = item.compression {
+                bundle::unpack(source, &item.original_path, compression)?;
+                fs::remove_file(source).context("Failed to remove bundle")?;
+            } else {
+                ContentStore::materialize(&self.config, source)?;
+                move_item(source, &item.original_path)
+                    .context("Failed to move item back to its inbox")?;
+            }
+            self.report(format!(
+                "↩️  Restored '{}' to {}",
+                item.name,
+                item.original_path.display()
+            ));
+        }
+
+        let leftovers = item
+            .stored_paths
+            .iter()
+            .filter(|path| *path != source)
+            .chain(&item.symlink_paths)
+            .chain(&item.epitaph_paths)
+            .chain(&item.epitaph_symlink_paths);
+
+        for path in leftovers {
+            // Symlinks may dangle by now, so look at the link itself
+            let Ok(metadata) = path.symlink_metadata() else {
+                continue;
+            };
+
+            if self.config.dry_run {
+                self.report(format!("🔍 Would remove {}", path.display()));
+                continue;
+            }
+
+            if metadata.is_dir() {
+                remove_dir_with_permissions(path)?;
+            } else {
+                fs::remove_file(path).context(format!("Failed to remove {}", path.display()))?;
+            }
+            self.report(format!("🧹 Removed {}", path.display()));
+            remove_empty_parents(path, &self.config.graveyard);
+        }
+
+        if !self.config.dry_run {
+            remove_empty_parents(source, &self.config.graveyard);
         }
+        GraveyardIndex::remove(&self.config, &item.stored_paths)?;
+        ContentStore::prune(&self.config)?;
 
-        Ok(ArchivedItem {})
+        Ok(())
+    }
+
+    /// Print progress for humans; machine-readable output only carries results.
+    fn report(&self, message: impl std::fmt::Display) {
+        if self.config.output.is_text() {
+            println!("{message}");
+        }
     }
 
     fn get_creation_time(&self, path: &Path) -> Result<DateTime<Utc>> {
@@ -119,45 +370,14 @@ impl Archiver {
         }
     }
 
-    fn find_original_subdirs(&self) -> Result<Vec<(String, DateTime<Utc>)>> {
-        let mut originals = Vec::new();
-        let now = Utc::now();
-
-        if self.config.path_format.created_subdir.is_original() {
-            originals.push((
-                self.config
-                    .path_format
-                    .created_subdir
-                    .get_name()
-                    .unwrap()
-                    .to_string(),
-                // Use creation time for created subdir - we'll set this properly later
-                now,
-            ));
-        }
-        if self.config.path_format.modified_subdir.is_original() {
-            originals.push((
-                self.config
-                    .path_format
-                    .modified_subdir
-                    .get_name()
-                    .unwrap()
-                    .to_string(),
-                // Use modification time for modified subdir - we'll set this properly later
-                now,
-            ));
-        }
-        if self.config.path_format.archived_subdir.is_original() {
-            originals.push((
-                self.config
-                    .path_format
-                    .archived_subdir
-                    .get_name()
-                    .unwrap()
-                    .to_string(),
-                now, // archived time
-            ));
-        }
+    fn find_original_views(&self) -> Result<Vec<View<'_>>> {
+        let originals: Vec<View> = self
+            .config
+            .path_format
+            .views()
+            .into_iter()
+            .filter(|view| view.subdir.is_original())
+            .collect();
 
         if originals.is_empty() {
             return Err(anyhow::anyhow!(
@@ -168,424 +388,187 @@ impl Archiver {
         Ok(originals)
     }
 
-    fn create_path_for_subdir(
-        &self,
-        subdir_name: &str,
-        name: &str,
-        time: DateTime<Utc>,
-    ) -> Result<PathBuf> {
-        let date_path = self.config.format_date_path(&time);
-
-        let mut path = self
+    /// `name`, or `name_1`, `name_2`... if it, or its epitaph, is already
+    /// taken in any of the views the item will show up in.
+    fn free_name(&self, item: &ViewItem, name: &str) -> String {
+        let views: Vec<View> = self
             .config
-            .graveyard
-            .join(subdir_name)
-            .join(date_path)
-            .join(name);
-
-        path = self.ensure_unique_name(path)?;
-        Ok(path)
+            .path_format
+            .views()
+            .into_iter()
+            .filter(|view| view.subdir.is_enabled())
+            .collect();
+        let is_free = |candidate: &str| {
+            views.iter().all(|view| {
+                let path = self.get_path_in_view(view, item, candidate);
+                path.symlink_metadata().is_err()
+                    && Epitaph::path_for(&path).symlink_metadata().is_err()
+            })
+        };
+
+        if is_free(name) {
+            return name.to_string();
+        }
+        (1..)
+            .map(|counter| numbered_name(name, counter))
+            .find(|candidate| is_free(candidate))
+            .expect("some numbered name is free")
     }
 
-    fn get_path_for_subdir(&self, subdir_name: &str, name: &str, time: DateTime<Utc>) -> PathBuf {
-        let date_path = self.config.format_date_path(&time);
-
+    fn get_path_in_view(&self, view: &View, item: &ViewItem, name: &str) -> PathBuf {
         self.config
-            .graveyard
-            .join(subdir_name)
-            .join(date_path)
-            .join(name)
+            .view_location(view, item, name)
+            .expect("enabled views have a name")
     }
 
     fn create_remaining_symlinks(
         &self,
         name: &str,
-        _created_paths: &std::collections::HashMap<String, PathBuf>,
-        created_time: DateTime<Utc>,
-        modified_time: DateTime<Utc>,
-        archived_time: DateTime<Utc>,
-    ) -> Result<()> {
-        let subdirs = [
-            (
-                "created",
-                &self.config.path_format.created_subdir,
-                created_time,
-            ),
-            (
-                "modified",
-                &self.config.path_format.modified_subdir,
-                modified_time,
-            ),
-            (
-                "archived",
-                &self.config.path_format.archived_subdir,
-                archived_time,
-            ),
-        ];
-
-        for (_subdir_type, subdir_config, time) in subdirs {
-            if !subdir_config.is_enabled() || subdir_config.is_original() {
-                continue; // Skip disabled subdirs and those that already have originals
-            }
+        created_paths: &std::collections::HashMap<String, PathBuf>,
+        item: &ViewItem,
+    ) -> Result<Vec<PathBuf>> {
+        let mut symlink_paths = Vec::new();
 
-            let subdir_name = subdir_config.get_name().unwrap();
+        for view in self.config.path_format.views() {
+            if !view.subdir.is_enabled() || view.subdir.is_original() {
+                continue; // Skip disabled views and those that already have originals
+            }
 
-            if let Some(target_subdir) = subdir_config.get_target() {
+            if let Some(target_subdir) = view.subdir.get_target() {
                 // Always create the path for the immediate target, not resolved chain
-                let target_time = if self.config.path_format.created_subdir.get_name()
-                    == Some(target_subdir)
-                {
-                    created_time
-                } else if self.config.path_format.modified_subdir.get_name() == Some(target_subdir)
-                {
-                    modified_time
-                } else if self.config.path_format.archived_subdir.get_name() == Some(target_subdir)
-                {
-                    archived_time
-                } else {
-                    time
+                let target_path = match created_paths.get(target_subdir) {
+                    Some(path) => path.clone(),
+                    None => match self.config.path_format.view(target_subdir) {
+                        Some(target) => self.get_path_in_view(&target, item, name),
+                        None => continue, // Nothing to point at
+                    },
                 };
-
-                let target_path = self.get_path_for_subdir(target_subdir, name, target_time);
-                let link_path = self.create_path_for_subdir(subdir_name, name, time)?;
+                let link_path = self.get_path_in_view(&view, item, name);
+
+                if self.config.dry_run {
+                    self.report(format!(
+                        "🔍 Would create symlink '{}' -> {}",
+                        link_path.display(),
+                        target_path.display()
+                    ));
+                    symlink_paths.push(link_path);
+                    continue;
+                }
 
                 self.ensure_directory_exists(link_path.parent().unwrap())?;
-                self.create_symlink(&target_path, &link_path)?;
-                println!(
+                create_relative_symlink(&target_path, &link_path)?;
+                self.report(format!(
                     "🔗 Created symlink '{}' -> {}",
                     link_path.display(),
                     target_path.display()
-                );
+                ));
+                symlink_paths.push(link_path);
             }
         }
 
-        Ok(())
-    }
-
-    fn ensure_unique_name(&self, mut path: PathBuf) -> Result<PathBuf> {
-        if !path.exists() {
-            return Ok(path);
-        }
-
-        let original_name = path
-            .file_name()
-            .context("Invalid path")?
-            .to_string_lossy()
-            .to_string();
-
-        let mut counter = 1;
-        loop {
-            let new_name = if let Some(dot_pos) = original_name.rfind('.') {
-                format!(
-                    "{}_{}{}",
-                    &original_name[..dot_pos],
-                    counter,
-                    &original_name[dot_pos..]
-                )
-            } else {
-                format!("{original_name}_{counter}")
-            };
-
-            path.set_file_name(new_name);
-
-            if !path.exists() {
-                break;
-            }
-
-            counter += 1;
-        }
-
-        Ok(path)
+        Ok(symlink_paths)
     }
 
+    /// Write the epitaph next to every stored copy and symlink it next to every
+    /// symlink view. Returns the written and the symlinked epitaph paths.
     fn save_epitaphs_with_logic(
         &self,
-        name: &str,
+        item: &StaleItem,
+        stored_name: &str,
         created_paths: &std::collections::HashMap<String, PathBuf>,
         note: &str,
-        created_time: &DateTime<Utc>,
-        modified_time: &DateTime<Utc>,
-        archived_time: &DateTime<Utc>,
-    ) -> Result<()> {
+        checksum: Option<&str>,
+        view_item: &ViewItem,
+    ) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
         // Create epitaph content once
-        let epitaph_content = format!(
-            "# Epitaph for {}\n\
-            # Archived: {}\n\
-            # Created: {}\n\
-            # Modified: {}\n\
-            # Hostname: {}\n\
-            \n\
-            {}",
-            name,
-            archived_time.format("%Y-%m-%d %H:%M:%S UTC"),
-            created_time.format("%Y-%m-%d %H:%M:%S UTC"),
-            modified_time.format("%Y-%m-%d %H:%M:%S UTC"),
-            self.config.get_hostname(),
-            note
-        );
+        let epitaph_content = Epitaph {
+            name: item.name.clone(),
+            archived: Some(view_item.archived),
+            created: Some(view_item.created),
+            modified: Some(view_item.modified),
+            hostname: Some(self.config.get_hostname()),
+            inbox: Some(item.inbox.clone()),
+            compressed: item.policy.compress,
+            checksum: checksum.map(str::to_string),
+            extra: Vec::new(),
+            note: note.to_string(),
+        }
+        .serialize();
 
-        let epitaph_filename = format!("{name}.epitaph");
-        let mut primary_epitaph_path = None;
+        let epitaph_filename = format!("{stored_name}.{EPITAPH_EXTENSION}");
+        let mut primary_epitaph_path: Option<PathBuf> = None;
         let mut created_epitaph_paths = std::collections::HashMap::new();
+        let mut epitaph_symlink_paths = Vec::new();
 
         // First, create epitaphs for all original file locations
         for (subdir_name, file_path) in created_paths {
             let epitaph_path = file_path.parent().unwrap().join(&epitaph_filename);
 
-            if primary_epitaph_path.is_none() {
+            if self.config.dry_run {
+                self.report(format!(
+                    "🔍 Would write epitaph to: {}",
+                    epitaph_path.display()
+                ));
+            } else if let Some(primary) = &primary_epitaph_path {
+                // Copy epitaph to additional original locations
+                fs::copy(primary, &epitaph_path).context("Failed to copy epitaph file")?;
+                self.report(format!("📄 Epitaph copied to: {}", epitaph_path.display()));
+            } else {
                 // Write the first epitaph
                 fs::write(&epitaph_path, &epitaph_content)
                     .context("Failed to write epitaph file")?;
                 primary_epitaph_path = Some(epitaph_path.clone());
-                println!("📄 Epitaph written to: {}", epitaph_path.display());
-            } else {
-                // Copy epitaph to additional original locations
-                fs::copy(primary_epitaph_path.as_ref().unwrap(), &epitaph_path)
-                    .context("Failed to copy epitaph file")?;
-                println!("📄 Epitaph copied to: {}", epitaph_path.display());
+                self.report(format!("📄 Epitaph written to: {}", epitaph_path.display()));
             }
 
             created_epitaph_paths.insert(subdir_name.clone(), epitaph_path);
         }
 
-        // Now create epitaph symlinks for symlink subdirs, following same logic as files
-        let subdirs = [
-            (
-                "created",
-                &self.config.path_format.created_subdir,
-                *created_time,
-            ),
-            (
-                "modified",
-                &self.config.path_format.modified_subdir,
-                *modified_time,
-            ),
-            (
-                "archived",
-                &self.config.path_format.archived_subdir,
-                *archived_time,
-            ),
-        ];
-
-        for (_subdir_type, subdir_config, time) in subdirs {
-            if !subdir_config.is_enabled() || subdir_config.is_original() {
-                continue; // Skip disabled subdirs and those that already have originals
+        // Now create epitaph symlinks for symlink views, following same logic as files
+        for view in self.config.path_format.views() {
+            if !view.subdir.is_enabled() || view.subdir.is_original() {
+                continue; // Skip disabled views and those that already have originals
             }
 
-            let subdir_name = subdir_config.get_name().unwrap();
-
-            if let Some(target_subdir) = subdir_config.get_target() {
-                // Get the target time for path resolution
-                let target_time = if self.config.path_format.created_subdir.get_name()
-                    == Some(target_subdir)
-                {
-                    *created_time
-                } else if self.config.path_format.modified_subdir.get_name() == Some(target_subdir)
-                {
-                    *modified_time
-                } else if self.config.path_format.archived_subdir.get_name() == Some(target_subdir)
-                {
-                    *archived_time
-                } else {
-                    time
+            if let Some(target_subdir) = view.subdir.get_target() {
+                let target_epitaph_path = match created_epitaph_paths.get(target_subdir) {
+                    Some(path) => path.clone(),
+                    None => match self.config.path_format.view(target_subdir) {
+                        Some(target) => {
+                            self.get_path_in_view(&target, view_item, &epitaph_filename)
+                        }
+                        None => continue, // Nothing to point at
+                    },
                 };
-
-                let target_epitaph_path =
-                    self.get_path_for_subdir(target_subdir, &epitaph_filename, target_time);
-                let link_epitaph_path =
-                    self.create_path_for_subdir(subdir_name, &epitaph_filename, time)?;
+                let link_epitaph_path = self.get_path_in_view(&view, view_item, &epitaph_filename);
+
+                if self.config.dry_run {
+                    self.report(format!(
+                        "🔍 Would create epitaph symlink '{}' -> {}",
+                        link_epitaph_path.display(),
+                        target_epitaph_path.display()
+                    ));
+                    epitaph_symlink_paths.push(link_epitaph_path);
+                    continue;
+                }
 
                 self.ensure_directory_exists(link_epitaph_path.parent().unwrap())?;
-                self.create_symlink(&target_epitaph_path, &link_epitaph_path)?;
-                println!(
+                create_relative_symlink(&target_epitaph_path, &link_epitaph_path)?;
+                self.report(format!(
                     "🔗 Created epitaph symlink '{}' -> {}",
                     link_epitaph_path.display(),
                     target_epitaph_path.display()
-                );
-            }
-        }
-
-        Ok(())
-    }
-
-    fn move_item(&self, src: &Path, dst: &Path) -> Result<()> {
-        // Try rename first (faster for same filesystem)
-        match fs::rename(src, dst) {
-            Ok(()) => Ok(()),
-            Err(e) if e.raw_os_error() == Some(18) => {
-                // Error 18 is "Invalid cross-device link" - use copy + remove instead
-                if src.is_dir() {
-                    copy_dir_all(src, dst)?;
-                    self.remove_dir_with_permissions(src)
-                        .context("Failed to remove source directory after copy")?;
-                } else {
-                    fs::copy(src, dst).context("Failed to copy file across devices")?;
-                    fs::remove_file(src).context("Failed to remove source file after copy")?;
-                }
-                Ok(())
+                ));
+                epitaph_symlink_paths.push(link_epitaph_path);
             }
-            Err(e) => Err(e.into()),
         }
-    }
 
-    fn remove_dir_with_permissions(&self, path: &Path) -> Result<()> {
-        // First try normal removal
-        match fs::remove_dir_all(path) {
-            Ok(()) => Ok(()),
-            Err(_) => {
-                // If that fails, try to fix permissions recursively and then remove
-                Self::fix_permissions_recursive(path)?;
-                fs::remove_dir_all(path)
-                    .context("Failed to remove directory even after fixing permissions")
-            }
-        }
-    }
-
-    fn fix_permissions_recursive(path: &Path) -> Result<()> {
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-
-            // Make the directory writable
-            if path.is_dir() {
-                let mut perms = fs::metadata(path)?.permissions();
-                perms.set_mode(perms.mode() | 0o700); // Add owner write/execute permissions
-                fs::set_permissions(path, perms)?;
-
-                // Recursively fix permissions for contents
-                if let Ok(entries) = fs::read_dir(path) {
-                    for entry in entries.flatten() {
-                        let entry_path = entry.path();
-                        if entry_path.is_dir() {
-                            Self::fix_permissions_recursive(&entry_path)?;
-                        } else {
-                            // Make files writable
-                            if let Ok(metadata) = fs::metadata(&entry_path) {
-                                let mut perms = metadata.permissions();
-                                perms.set_mode(perms.mode() | 0o600); // Add owner read/write permissions
-                                let _ = fs::set_permissions(&entry_path, perms);
-                                // Ignore errors for broken symlinks
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        #[cfg(windows)]
-        {
-            // On Windows, try to remove read-only attribute
-            let _ = fs::metadata(path).and_then(|metadata| {
-                let mut perms = metadata.permissions();
-                perms.set_readonly(false);
-                fs::set_permissions(path, perms)
-            });
-        }
-        Ok(())
+        let mut epitaph_paths: Vec<PathBuf> = created_epitaph_paths.into_values().collect();
+        epitaph_paths.sort();
+        Ok((epitaph_paths, epitaph_symlink_paths))
     }
 
     fn ensure_directory_exists(&self, path: &Path) -> Result<()> {
         fs::create_dir_all(path).context("Failed to create directory")
     }
-
-    fn create_symlink(&self, target: &Path, link: &Path) -> Result<()> {
-        // Calculate relative path from link to target to avoid cross-device issues
-        let relative_target = self.calculate_relative_path(link.parent().unwrap(), target)?;
-
-        #[cfg(unix)]
-        {
-            std::os::unix::fs::symlink(&relative_target, link)
-                .context("Failed to create symlink")?;
-        }
-
-        #[cfg(windows)]
-        {
-            if target.is_dir() {
-                std::os::windows::fs::symlink_dir(&relative_target, link)
-                    .context("Failed to create directory symlink")?;
-            } else {
-                std::os::windows::fs::symlink_file(&relative_target, link)
-                    .context("Failed to create file symlink")?;
-            }
-        }
-
-        Ok(())
-    }
-
-    fn calculate_relative_path(&self, from_dir: &Path, to_path: &Path) -> Result<PathBuf> {
-        let from_abs = from_dir
-            .canonicalize()
-            .unwrap_or_else(|_| from_dir.to_path_buf());
-        let to_abs = to_path
-            .canonicalize()
-            .unwrap_or_else(|_| to_path.to_path_buf());
-
-        // Find common ancestor
-        let from_components: Vec<_> = from_abs.components().collect();
-        let to_components: Vec<_> = to_abs.components().collect();
-
-        let common_len = from_components
-            .iter()
-            .zip(to_components.iter())
-            .take_while(|(a, b)| a == b)
-            .count();
-
-        // Build relative path
-        let mut relative_path = PathBuf::new();
-
-        // Add ".." for each directory we need to go up from the common ancestor
-        for _ in common_len..from_components.len() {
-            relative_path.push("..");
-        }
-
-        // Add the path components from common ancestor to target
-        for component in &to_components[common_len..] {
-            relative_path.push(component.as_os_str());
-        }
-
-        // Handle the case where we're in the same directory
-        if relative_path.as_os_str().is_empty() {
-            relative_path.push(to_path.file_name().unwrap());
-        }
-
-        Ok(relative_path)
-    }
-}
-
-fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
-    fs::create_dir_all(dst)?;
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let ty = entry.file_type()?;
-        let src_path = entry.path();
-        let dst_path = dst.join(entry.file_name());
-
-        if ty.is_dir() {
-            copy_dir_all(&src_path, &dst_path)?;
-        } else if ty.is_symlink() {
-            // Handle symlinks by copying the symlink itself, not following it
-            #[cfg(unix)]
-            {
-                if let Ok(target) = fs::read_link(&src_path) {
-                    std::os::unix::fs::symlink(&target, &dst_path)
-                        .context("Failed to copy symlink")?;
-                }
-            }
-            #[cfg(windows)]
-            {
-                // On Windows, try to copy as file/dir symlink based on target
-                if let Ok(target) = fs::read_link(&src_path) {
-                    if target.is_dir() {
-                        std::os::windows::fs::symlink_dir(&target, &dst_path)
-                            .context("Failed to copy directory symlink")?;
-                    } else {
-                        std::os::windows::fs::symlink_file(&target, &dst_path)
-                            .context("Failed to copy file symlink")?;
-                    }
-                }
-            }
-        } else {
-            fs::copy(&src_path, &dst_path).context("Failed to copy file")?;
-        }
-    }
-    Ok(())
 }
@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::config::Compression;
+use crate::utils::{copy_times, delete_item, move_item};
+
+/// What a bundle holds, read from its tar headers.
+pub struct BundleContents {
+    pub is_directory: bool,
+    /// Uncompressed size of every file in the bundle
+    pub size: u64,
+    /// Paths inside a bundled folder, relative to the folder
+    pub members: Vec<String>,
+}
+
+/// Pack the file or folder at `source` into a compressed tar at `bundle`.
+/// The tar holds a single top-level entry named after `source`. Returns the
+/// member paths, as `contents` would list them.
+pub fn pack(source: &Path, bundle: &Path, compression: Compression) -> Result<Vec<String>> {
+    let file = BufWriter::new(File::create(bundle).context("Failed to create bundle")?);
+    let file = match compression {
+        Compression::Gzip => {
+            let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            write_tar(source, encoder)?.finish()?
+        }
+        Compression::Zstd => {
+            let encoder = zstd::Encoder::new(file, 0).context("Failed to start zstd encoder")?;
+            write_tar(source, encoder)?.finish()?
+        }
+        Compression::None => anyhow::bail!("Cannot bundle without a compression format"),
+    };
+    file.into_inner()
+        .map_err(|e| e.into_error())
+        .context("Failed to write bundle")?;
+
+    // The bundle stands in for the item, so it keeps the item's timestamps
+    copy_times(source, bundle)?;
+
+    let mut members = Vec::new();
+    for entry in WalkDir::new(source).min_depth(1).sort_by_file_name() {
+        let entry = entry?;
+        let member = entry.path().strip_prefix(source).unwrap_or(entry.path());
+        members.push(member.to_string_lossy().to_string());
+    }
+    Ok(members)
+}
+
+fn write_tar<W: Write>(source: &Path, writer: W) -> Result<W> {
+    let name = source
+        .file_name()
+        .context("Cannot bundle a path without a file name")?;
+
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    if source.is_dir() {
+        builder.append_dir_all(name, source)
+    } else {
+        builder.append_path_with_name(source, name)
+    }
+    .context(format!("Failed to bundle {}", source.display()))?;
+    builder.into_inner().context("Failed to finish bundle")
+}
+
+/// Unpack `bundle` so its top-level entry ends up at `dest`.
+pub fn unpack(bundle: &Path, dest: &Path, compression: Compression) -> Result<()> {
+    let parent = dest.parent().context("Invalid unpack destination")?;
+    let name = dest.file_name().unwrap_or_default().to_string_lossy();
+    let staging = parent.join(format!(".{name}.relfa-unpack"));
+    if staging.symlink_metadata().is_ok() {
+        delete_item(&staging)?;
+    }
+    fs::create_dir_all(&staging).context("Failed to create unpack directory")?;
+
+    let result = (|| {
+        let mut archive = tar::Archive::new(open(bundle, compression)?);
+        archive.set_preserve_mtime(true);
+        archive.set_preserve_permissions(true);
+
+        // Unpacking files touches their folders, so folder times are set last
+        let mut folder_times = Vec::new();
+        for entry in archive
+            .entries()
+            .context(format!("Failed to read {}", bundle.display()))?
+        {
+            let mut entry = entry?;
+            if entry.header().entry_type().is_dir() {
+                let mtime = entry.header().mtime()?;
+                folder_times.push((staging.join(entry.path()?), mtime));
+            }
+            entry
+                .unpack_in(&staging)
+                .context(format!("Failed to unpack {}", bundle.display()))?;
+        }
+        for (folder, mtime) in folder_times.iter().rev() {
+            let time = filetime::FileTime::from_unix_time(*mtime as i64, 0);
+            filetime::set_file_mtime(folder, time)?;
+        }
+
+        let mut entries = fs::read_dir(&staging)?;
+        let (Some(entry), None) = (entries.next().transpose()?, entries.next()) else {
+            anyhow::bail!("{} does not hold a single item", bundle.display());
+        };
+        move_item(&entry.path(), dest)
+    })();
+
+    fs::remove_dir_all(&staging).ok();
+    result
+}
+
+/// List what `bundle` holds without unpacking it.
+pub fn contents(bundle: &Path, compression: Compression) -> Result<BundleContents> {
+    let mut archive = tar::Archive::new(open(bundle, compression)?);
+    let mut contents = BundleContents {
+        is_directory: false,
+        size: 0,
+        members: Vec::new(),
+    };
+
+    for entry in archive
+        .entries()
+        .context(format!("Failed to read {}", bundle.display()))?
+    {
+        let entry = entry?;
+        let path = entry.path()?.into_owned();
+        let mut components = path.components();
+        components.next();
+        let member = components.as_path();
+
+        if member.as_os_str().is_empty() {
+            contents.is_directory = entry.header().entry_type().is_dir();
+        } else {
+            contents.members.push(member.to_string_lossy().to_string());
+        }
+        if entry.header().entry_type().is_file() {
+            contents.size += entry.size();
+        }
+    }
+
+    Ok(contents)
+}
+
+fn open(bundle: &Path, compression: Compression) -> Result<Box<dyn Read>> {
+    let file = BufReader::new(
+        File::open(bundle).context(format!("Failed to open bundle {}", bundle.display()))?,
+    );
+    Ok(match compression {
+        Compression::Gzip => Box::new(flate2::read::GzDecoder::new(file)),
+        Compression::Zstd => {
+            Box::new(zstd::Decoder::with_buffer(file).context("Failed to start zstd decoder")?)
+        }
+        Compression::None => Box::new(file),
+    })
+}
@@ -1,4 +1,9 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use std::path::PathBuf;
+
+use crate::graveyard::ConflictPolicy;
+use crate::output::OutputFormat;
 
 #[derive(Parser)]
 #[command(
@@ -8,6 +13,20 @@ use clap::{Parser, Subcommand};
     long_about = "Relfa helps you keep your computer's clutter under control by monitoring your Inbox folder, nudging you to review old files, and lovingly archiving them in a dust-covered, cobwebby digital Graveyard."
 )]
 pub struct Cli {
+    #[arg(
+        long,
+        global = true,
+        help = "Show what would be changed without touching the disk"
+    )]
+    pub dry_run: bool,
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format for scan, search, archive and fsck results"
+    )]
+    pub format: OutputFormat,
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -17,13 +36,24 @@ pub enum Commands {
     #[command(about = "Scan Inbox for stale files")]
     Scan,
     #[command(about = "Interactively review and archive files")]
-    Review,
+    Review {
+        #[arg(
+            long,
+            help = "Go through the items one at a time instead of in the full-screen list"
+        )]
+        plain: bool,
+    },
     #[command(about = "Archive files to the Graveyard")]
     Archive {
         #[arg(help = "Specific file or folder to archive")]
         item: Option<String>,
         #[arg(long, help = "Archive all eligible files")]
         all: bool,
+        #[arg(
+            long,
+            help = "Name of the inbox holding the item (defaults to the first inbox that has it)"
+        )]
+        inbox: Option<String>,
         #[arg(long, help = "Add an epitaph (note) explaining why this was archived")]
         note: Option<String>,
         #[arg(
@@ -32,16 +62,197 @@ pub enum Commands {
         )]
         no_auto_archive: bool,
     },
-    #[command(about = "Resurrect files from the Graveyard back to Inbox (copies, doesn't remove)")]
+    #[command(about = "Leave an inbox item out of scans for a while, without touching it")]
+    Snooze {
+        #[arg(help = "File or folder in the inbox")]
+        item: String,
+        #[arg(
+            long = "for",
+            value_name = "DURATION",
+            help = "How long to snooze it, e.g. 3d, 2w or 6m (default: 1w)"
+        )]
+        duration: Option<String>,
+        #[arg(
+            long,
+            value_name = "DATE",
+            conflicts_with = "duration",
+            help = "Snooze it until DATE (YYYY-MM-DD)"
+        )]
+        until: Option<String>,
+        #[arg(
+            long,
+            help = "Name of the inbox holding the item (defaults to the first inbox that has it)"
+        )]
+        inbox: Option<String>,
+    },
+    #[command(about = "Leave an inbox item out of scans for good, without touching it")]
+    Pin {
+        #[arg(help = "File or folder in the inbox")]
+        item: String,
+        #[arg(
+            long,
+            help = "Name of the inbox holding the item (defaults to the first inbox that has it)"
+        )]
+        inbox: Option<String>,
+    },
+    #[command(about = "Lift the pin or snooze of an inbox item")]
+    Unpin {
+        #[arg(help = "File or folder in the inbox")]
+        item: String,
+        #[arg(
+            long,
+            help = "Name of the inbox holding the item (defaults to the first inbox that has it)"
+        )]
+        inbox: Option<String>,
+    },
+    #[command(about = "List pinned and snoozed inbox items")]
+    Pins,
+    #[command(
+        about = "Resurrect files from the Graveyard back to their Inbox (copies unless --move)"
+    )]
     Resurrect {
-        #[arg(help = "Pattern to search for in the graveyard")]
-        pattern: String,
+        #[arg(
+            required_unless_present = "id",
+            help = "Pattern to search for in the graveyard"
+        )]
+        pattern: Option<String>,
+        #[arg(
+            long,
+            value_name = "N",
+            group = "selection",
+            help = "Resurrect the Nth listed match"
+        )]
+        index: Option<usize>,
+        #[arg(
+            long,
+            group = "selection",
+            help = "Resurrect the most recently archived match"
+        )]
+        latest: bool,
+        #[arg(long, group = "selection", help = "Resurrect every match")]
+        all: bool,
+        #[arg(
+            long,
+            value_name = "ARCHIVE_ID",
+            group = "selection",
+            help = "Resurrect the archived item with this id (or a unique prefix)"
+        )]
+        id: Option<String>,
+        #[arg(
+            long,
+            short = 'y',
+            help = "Never prompt; fail when a choice would be needed"
+        )]
+        yes: bool,
+        #[arg(
+            long = "move",
+            help = "Remove the item from the Graveyard, with its copies, symlink views and epitaphs"
+        )]
+        move_item: bool,
+        #[arg(
+            long,
+            value_name = "DIR",
+            help = "Resurrect into DIR instead of the inbox the item came from"
+        )]
+        to: Option<PathBuf>,
+        #[arg(long, help = "Keep the archived modification and access times")]
+        preserve_times: bool,
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = ConflictPolicy::Rename,
+            help = "What to do when the destination already exists"
+        )]
+        on_conflict: ConflictPolicy,
     },
     #[command(about = "Search for files in the Graveyard")]
     Search {
-        #[arg(help = "Pattern to search for")]
-        pattern: String,
+        #[command(flatten)]
+        query: SearchArgs,
+    },
+    #[command(about = "Rebuild the Graveyard index from the archived files and epitaphs")]
+    Reindex,
+    #[command(
+        about = "Verify archived files against their checksums and look for missing or orphaned entries"
+    )]
+    Fsck,
+    #[command(about = "Maintain the Graveyard itself")]
+    Graveyard {
+        #[command(subcommand)]
+        action: GraveyardCommand,
+    },
+    #[command(about = "Undo operations recorded in the journal, newest first")]
+    Undo {
+        #[arg(long, help = "Number of operations to undo (default: 1)")]
+        last: Option<usize>,
+        #[arg(
+            long,
+            conflicts_with = "last",
+            help = "Id of the journal entry to undo"
+        )]
+        id: Option<u64>,
     },
     #[command(about = "Show or edit configuration")]
     Config,
 }
+
+#[derive(Subcommand)]
+pub enum GraveyardCommand {
+    #[command(
+        about = "Re-point dangling symlink views at their original and remove orphaned links and epitaphs"
+    )]
+    Repair,
+    #[command(about = "Move every archived item into the layout currently set in path_format")]
+    Migrate,
+}
+
+#[derive(Args)]
+pub struct SearchArgs {
+    #[arg(help = "Pattern to search for in names and epitaphs (omit to list everything)")]
+    pub pattern: Option<String>,
+    #[arg(
+        long,
+        conflicts_with = "glob",
+        help = "Treat the pattern as a regular expression"
+    )]
+    pub regex: bool,
+    #[arg(long, help = "Treat the pattern as a glob matched against whole names")]
+    pub glob: bool,
+    #[arg(long, help = "Match the pattern against epitaph notes only")]
+    pub epitaph_only: bool,
+    #[arg(
+        long,
+        value_name = "DATE",
+        help = "Only items archived on or after DATE (YYYY, YYYY-MM or YYYY-MM-DD)"
+    )]
+    pub archived_after: Option<String>,
+    #[arg(
+        long,
+        value_name = "DATE",
+        help = "Only items archived before DATE (YYYY, YYYY-MM or YYYY-MM-DD)"
+    )]
+    pub archived_before: Option<String>,
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["START", "END"],
+        help = "Only items last modified between START and END, both included"
+    )]
+    pub modified_between: Option<Vec<String>>,
+    #[arg(long, help = "Only items archived from this host")]
+    pub host: Option<String>,
+    #[arg(
+        long,
+        value_name = "SIZE",
+        help = "Only items larger than SIZE (e.g. 10MB)"
+    )]
+    pub larger_than: Option<String>,
+    #[arg(long = "type", value_enum, help = "Only files or only folders")]
+    pub item_type: Option<ItemType>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ItemType {
+    File,
+    Dir,
+}
@@ -1,17 +1,39 @@
-use anyhow::Result;
-use chrono::{DateTime, Utc};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
 use crossterm::{
     event::{self, Event, KeyCode},
     terminal::{disable_raw_mode, enable_raw_mode},
 };
-use std::io::{self, Write};
+use serde::Serialize;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
 
-use crate::archiver::Archiver;
-use crate::config::Config;
-use crate::graveyard::GraveyardManager;
+use crate::archiver::{ArchivedItem, Archiver};
+use crate::bundle;
+use crate::cli::SearchArgs;
+use crate::config::{Config, Inbox, RuleAction, Settings};
+use crate::epitaph::Epitaph;
+use crate::fsck::{self, IssueKind};
+use crate::graveyard::{GraveyardManager, ResurrectOptions};
+use crate::index::GraveyardIndex;
+use crate::journal::{Journal, JournalEntry, Operation};
+use crate::lock::Lock;
+use crate::migrate::Migration;
+use crate::output::{print_records, OutputFormat};
+use crate::query::SearchQuery;
+use crate::repair;
+use crate::rules::RuleSet;
 use crate::scanner::{Scanner, StaleItem};
-use crate::state::NotificationState;
-use crate::utils::{delete_item, open_file_with_default, touch_item, view_file_with_pager};
+use crate::state::{FileIdentity, Hold, NotificationState};
+use crate::store::ContentStore;
+use crate::tui;
+use crate::utils::{
+    delete_item, item_hash, move_item, open_file_with_default, parse_duration, touch_item,
+    view_file_with_pager,
+};
+
+/// How long review and `relfa snooze` snooze an item unless told otherwise.
+pub const DEFAULT_SNOOZE: &str = "1w";
 
 fn get_single_keypress() -> Result<char> {
     enable_raw_mode()?;
@@ -33,17 +55,141 @@ fn get_single_keypress() -> Result<char> {
     Ok(key)
 }
 
-pub fn scan_inbox() -> Result<()> {
-    let config = Config::load_without_save()?;
+fn load_config(dry_run: bool, output: OutputFormat) -> Result<Config> {
+    Ok(Config::new(Settings::load_without_save()?, dry_run, output))
+}
+
+/// Print progress for humans; machine-readable output only carries results.
+fn say(config: &Config, message: impl std::fmt::Display) {
+    if config.output.is_text() {
+        println!("{message}");
+    }
+}
+
+/// One entry of machine-readable archive output.
+#[derive(Debug, Serialize)]
+struct ArchiveResult {
+    action: RuleAction,
+    name: String,
+    inbox: String,
+    path: PathBuf,
+    dry_run: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archived: Option<ArchivedItem>,
+}
+
+impl ArchiveResult {
+    fn new(item: &StaleItem, action: RuleAction, config: &Config) -> Self {
+        Self {
+            action,
+            name: item.name.clone(),
+            inbox: item.inbox.clone(),
+            path: item.path.clone(),
+            dry_run: config.dry_run,
+            archived: None,
+        }
+    }
+}
+
+/// Bump the item's modification time so it is kept for another period.
+pub fn touch_stale_item(item: &StaleItem, config: &Config) -> Result<()> {
+    // Reset notification count since file was touched
+    let _lock = Lock::state(config)?;
+    let mut state = NotificationState::load().unwrap_or_default();
+    if config.dry_run {
+        println!(
+            "🔍 Would touch '{}' to keep it for another {} days",
+            item.path.display(),
+            item.policy.age_threshold_days
+        );
+    } else {
+        touch_item(&item.path)?;
+        let tracked = state.tracked(&item.inbox, &item.identity);
+        Journal::record(
+            config,
+            Operation::Touch {
+                inbox: item.inbox.clone(),
+                name: item.name.clone(),
+                path: item.path.clone(),
+                previous_modified: item.last_modified,
+                notification_count: tracked.map_or(0, |tracked| tracked.count),
+                first_seen: tracked.and_then(|tracked| tracked.first_seen),
+                notified: tracked
+                    .map(|tracked| tracked.notified.clone())
+                    .unwrap_or_default(),
+            },
+        );
+    }
+    state.reset_notification_count(&item.inbox, &item.identity);
+    state.save_unless_dry_run(
+        config,
+        &format!("reset notification count for '{}'", item.name),
+    )?;
+    if !config.dry_run {
+        println!(
+            "✨ Updated modification time for '{}' - file will be kept for another {} days",
+            item.name, item.policy.age_threshold_days
+        );
+    }
+    Ok(())
+}
+
+pub fn delete_stale_item(item: &StaleItem, config: &Config) -> Result<()> {
+    if config.dry_run {
+        say(
+            config,
+            format!("🔍 Would permanently delete '{}'", item.path.display()),
+        );
+    } else {
+        delete_item(&item.path)?;
+        Journal::record(
+            config,
+            Operation::Delete {
+                inbox: item.inbox.clone(),
+                name: item.name.clone(),
+                path: item.path.clone(),
+            },
+        );
+    }
+
+    // Reset notification count since file was deleted
+    let _lock = Lock::state(config)?;
+    let mut state = NotificationState::load().unwrap_or_default();
+    state.reset_notification_count(&item.inbox, &item.identity);
+    state.save_unless_dry_run(
+        config,
+        &format!("reset notification count for '{}'", item.name),
+    )?;
+
+    if !config.dry_run {
+        say(config, format!("🗑️  Permanently deleted '{}'", item.name));
+    }
+    Ok(())
+}
+
+pub fn scan_inbox(dry_run: bool, output: OutputFormat) -> Result<()> {
+    let config = load_config(dry_run, output)?;
     let scanner = Scanner::new(config);
-    // Use the version that tracks notifications
-    let stale_items = scanner.scan_inbox_with_notification_tracking()?;
-    scanner.display_scan_results(&stale_items);
+    // Only a scan shown to a person counts as a notification: status bars
+    // polling the JSON output would otherwise fill the notified days alone
+    let stale_items = if output.is_text() {
+        scanner.scan_inbox_with_notification_tracking()?
+    } else {
+        scanner.scan_inbox()?
+    };
+    if output.is_text() {
+        scanner.display_scan_results(&stale_items);
+    } else {
+        print_records(output, &scanner.scan_records(&stale_items)?)?;
+    }
     Ok(())
 }
 
-pub fn interactive_review() -> Result<()> {
-    let config = Config::load_without_save()?;
+pub fn interactive_review(dry_run: bool, plain: bool) -> Result<()> {
+    let config = load_config(dry_run, OutputFormat::Text)?;
+    if !plain && io::stdin().is_terminal() && io::stdout().is_terminal() {
+        return tui::run(config);
+    }
     let scanner = Scanner::new(config.clone());
     let archiver = Archiver::new(config.clone());
 
@@ -64,26 +210,11 @@ pub fn interactive_review() -> Result<()> {
 
         // Show help automatically for the first file
         if i == 0 {
-            println!("\n📚 Available actions:");
-            println!("  a - Archive: Move the file to the graveyard");
-            println!("  n - Note+archive: Archive with an epitaph (descriptive note)");
-            println!(
-                "  t - Touch: Update modification time to keep for another {} days",
-                config.age_threshold_days
-            );
-            println!("  d - Delete: Permanently delete the file (requires confirmation)");
-            println!(
-                "  v - View: Preview file content using pager ({})",
-                config.pager
-            );
-            println!("  o - Open: Open file with default application");
-            println!("  s - Skip: Skip this file and move to the next");
-            println!("  q - Quit: Exit the review session");
-            println!("  ? - Help: Show this help message\n");
+            print_review_help(&config);
         }
 
         loop {
-            print!("Action [a/n/t/d/v/o/s/q/?]: ");
+            print!("Action [a/n/t/z/p/d/v/o/s/q/?]: ");
             io::stdout().flush()?;
 
             let input = get_single_keypress()?.to_lowercase().next().unwrap_or('\0');
@@ -113,13 +244,46 @@ pub fn interactive_review() -> Result<()> {
                     break;
                 }
                 't' => {
-                    touch_item(&item.path)?;
-                    // Reset notification count since file was touched
-                    let mut state = NotificationState::load().unwrap_or_default();
-                    state.reset_notification_count(&item.name);
-                    state.save()?;
-                    println!("✨ Updated modification time for '{}' - file will be kept for another {} days", 
-                             item.name, config.age_threshold_days);
+                    touch_stale_item(item, &config)?;
+                    skipped_count += 1; // Count as skipped since we're keeping it
+                    break;
+                }
+                'z' => {
+                    // Temporarily disable raw mode for the duration input
+                    disable_raw_mode()?;
+                    print!("💤 Snooze for how long? (e.g. 3d, 2w, 6m) [{DEFAULT_SNOOZE}]: ");
+                    io::stdout().flush()?;
+
+                    let mut duration = String::new();
+                    io::stdin().read_line(&mut duration)?;
+                    let duration = Some(duration.trim()).filter(|duration| !duration.is_empty());
+
+                    match snooze_end(duration, None) {
+                        Ok(end) => {
+                            set_item_hold(
+                                &config,
+                                &item.inbox,
+                                &item.name,
+                                &item.path,
+                                Some(Hold::SnoozedUntil(end)),
+                            )?;
+                            skipped_count += 1; // Count as skipped since we're keeping it
+                            break;
+                        }
+                        Err(e) => {
+                            println!("❌ {e}");
+                            continue;
+                        }
+                    }
+                }
+                'p' => {
+                    set_item_hold(
+                        &config,
+                        &item.inbox,
+                        &item.name,
+                        &item.path,
+                        Some(Hold::Pinned),
+                    )?;
                     skipped_count += 1; // Count as skipped since we're keeping it
                     break;
                 }
@@ -141,12 +305,7 @@ pub fn interactive_review() -> Result<()> {
                     let confirmation = confirmation.trim();
 
                     if confirmation == item.name {
-                        delete_item(&item.path)?;
-                        // Reset notification count since file was deleted
-                        let mut state = NotificationState::load().unwrap_or_default();
-                        state.reset_notification_count(&item.name);
-                        state.save()?;
-                        println!("🗑️  Permanently deleted '{}'", item.name);
+                        delete_stale_item(item, &config)?;
                         archived_count += 1; // Count as "processed"
                         break;
                     } else {
@@ -175,22 +334,7 @@ pub fn interactive_review() -> Result<()> {
                     return Ok(());
                 }
                 '?' => {
-                    println!("\n📚 Available actions:");
-                    println!("  a - Archive: Move the file to the graveyard");
-                    println!("  n - Note+archive: Archive with an epitaph (descriptive note)");
-                    println!(
-                        "  t - Touch: Update modification time to keep for another {} days",
-                        config.age_threshold_days
-                    );
-                    println!("  d - Delete: Permanently delete the file (requires confirmation)");
-                    println!(
-                        "  v - View: Preview file content using pager ({})",
-                        config.pager
-                    );
-                    println!("  o - Open: Open file with default application");
-                    println!("  s - Skip: Skip this file and move to the next");
-                    println!("  q - Quit: Exit the review session");
-                    println!("  ? - Help: Show this help message\n");
+                    print_review_help(&config);
                     continue;
                 }
                 _ => {
@@ -208,45 +352,125 @@ pub fn interactive_review() -> Result<()> {
     Ok(())
 }
 
-pub fn archive_all_with_note(note: Option<&str>) -> Result<()> {
-    let config = Config::load_without_save()?;
+fn print_review_help(config: &Config) {
+    println!("\n📚 Available actions:");
+    println!("  a - Archive: Move the file to the graveyard");
+    println!("  n - Note+archive: Archive with an epitaph (descriptive note)");
+    println!(
+        "  t - Touch: Update modification time to keep for another {} days",
+        config.age_threshold_days
+    );
+    println!("  z - Snooze: Leave the file out of scans for a while, without touching it");
+    println!("  p - Pin: Leave the file out of scans for good, without touching it");
+    println!("  d - Delete: Permanently delete the file (requires confirmation)");
+    println!(
+        "  v - View: Preview file content using pager ({})",
+        config.pager
+    );
+    println!("  o - Open: Open file with default application");
+    println!("  s - Skip: Skip this file and move to the next");
+    println!("  q - Quit: Exit the review session");
+    println!("  ? - Help: Show this help message\n");
+}
+
+pub fn archive_all_with_note(
+    note: Option<&str>,
+    dry_run: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let config = load_config(dry_run, output)?;
     let scanner = Scanner::new(config.clone());
-    let archiver = Archiver::new(config);
+    let archiver = Archiver::new(config.clone());
 
-    let stale_items = scanner.scan_inbox()?;
+    // Items kept by a rule are never archived in bulk
+    let stale_items: Vec<StaleItem> = scanner
+        .scan_inbox()?
+        .into_iter()
+        .filter(|item| item.policy.action != RuleAction::Keep)
+        .collect();
 
     if stale_items.is_empty() {
-        println!("✨ No items to archive - Inbox is clean!");
-        return Ok(());
+        say(&config, "✨ No items to archive - Inbox is clean!");
+        return print_records::<ArchiveResult>(config.output, &[]);
     }
 
-    println!("Found {} stale items to archive:", stale_items.len());
+    say(
+        &config,
+        format!("Found {} stale items to archive:", stale_items.len()),
+    );
+    let mut results = Vec::new();
     for item in &stale_items {
-        archiver.archive_item_with_note(item, note)?;
+        let mut result = ArchiveResult::new(item, RuleAction::Archive, &config);
+        result.archived = Some(archiver.archive_item_with_note(item, note)?);
+        results.push(result);
     }
+    print_records(config.output, &results)?;
 
-    println!(
-        "\n🎉 Successfully archived {} items to the Graveyard!",
-        stale_items.len()
+    if config.dry_run {
+        say(
+            &config,
+            format!(
+                "\n🔍 Dry run complete: {} items would be archived",
+                stale_items.len()
+            ),
+        );
+        return Ok(());
+    }
+
+    say(
+        &config,
+        format!(
+            "\n🎉 Successfully archived {} items to the Graveyard!",
+            stale_items.len()
+        ),
     );
     Ok(())
 }
 
-pub fn archive_item_with_note(item_name: &str, note: Option<&str>) -> Result<()> {
-    let config = Config::load_without_save()?;
+pub fn archive_item_with_note(
+    item_name: &str,
+    inbox_name: Option<&str>,
+    note: Option<&str>,
+    dry_run: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let config = load_config(dry_run, output)?;
     let scanner = Scanner::new(config.clone());
     let archiver = Archiver::new(config.clone());
 
+    // Find the inbox holding the item: the requested one, or the first that has it
+    let inbox = match inbox_name {
+        Some(name) => match config.find_inbox(name) {
+            Some(inbox) => Some(inbox),
+            None => {
+                if !config.output.is_text() {
+                    anyhow::bail!("No inbox named '{name}' is configured");
+                }
+                say(&config, format!("❌ No inbox named '{name}' is configured"));
+                return Ok(());
+            }
+        },
+        None => config
+            .inboxes()
+            .into_iter()
+            .find(|inbox| inbox.path.join(item_name).exists()),
+    };
+
     // First, check if the file exists in the Inbox at all
-    let inbox_path = config.inbox.join(item_name);
-    if !inbox_path.exists() {
-        println!("❌ Item '{item_name}' not found in Inbox");
+    let Some(inbox) = inbox.filter(|inbox| inbox.path.join(item_name).exists()) else {
+        if !config.output.is_text() {
+            anyhow::bail!("Item '{item_name}' not found in Inbox");
+        }
+        say(&config, format!("❌ Item '{item_name}' not found in Inbox"));
         return Ok(());
-    }
+    };
+    let inbox_path = inbox.path.join(item_name);
 
     // Check if it's among the stale items (for potential warning)
     let stale_items = scanner.scan_inbox()?;
-    let is_stale = stale_items.iter().any(|i| i.name == item_name);
+    let is_stale = stale_items
+        .iter()
+        .any(|i| i.name == item_name && i.inbox == inbox.name);
 
     // Create a StaleItem for this file regardless of age
     let metadata = std::fs::metadata(&inbox_path)?;
@@ -260,88 +484,654 @@ pub fn archive_item_with_note(item_name: &str, note: Option<&str>) -> Result<()>
 
     // Get notification count for this item
     let state = NotificationState::load().unwrap_or_default();
-    let notification_count = state.get_notification_count(item_name);
+    let identity = FileIdentity::of(item_name, &inbox_path);
+    let notification_count = state.get_notification_count(&inbox.name, &identity);
+    let first_seen = state.first_seen(&inbox.name, &identity);
+    let notified = state.notification_spread(&inbox.name, &identity);
+    let policy = RuleSet::new(&config)?.policy_for(&inbox, &inbox_path)?;
 
     let item = StaleItem {
         path: inbox_path.clone(),
         name: item_name.to_string(),
+        inbox: inbox.name.clone(),
         last_modified,
         is_directory: inbox_path.is_dir(),
         age_days,
         notification_count,
+        first_seen,
+        notified,
+        policy,
+        identity,
     };
 
     // Show warning if file is not stale
     if !is_stale {
-        println!(
-            "⚠️  Warning: '{}' is only {} days old (threshold: {} days)",
-            item_name, age_days, config.age_threshold_days
+        say(
+            &config,
+            format!(
+                "⚠️  Warning: '{}' is only {} days old (threshold: {} days)",
+                item_name, age_days, inbox.age_threshold_days
+            ),
         );
-        println!("📦 Archiving anyway as explicitly requested...");
+        say(&config, "📦 Archiving anyway as explicitly requested...");
     }
 
-    archiver.archive_item_with_note(&item, note)?;
-    println!("🎉 Successfully archived '{item_name}'!");
+    let mut result = ArchiveResult::new(&item, RuleAction::Archive, &config);
+    result.archived = Some(archiver.archive_item_with_note(&item, note)?);
+    print_records(config.output, &[result])?;
+    if !config.dry_run {
+        say(&config, format!("🎉 Successfully archived '{item_name}'!"));
+    }
 
     Ok(())
 }
 
-pub fn resurrect_files(pattern: &str) -> Result<()> {
-    let config = Config::load_without_save()?;
+/// The inbox holding `item_name` and the item's path: the requested inbox, or
+/// the first that has it.
+fn find_inbox_item(
+    config: &Config,
+    item_name: &str,
+    inbox_name: Option<&str>,
+) -> Result<(Inbox, PathBuf)> {
+    let inbox = match inbox_name {
+        Some(name) => Some(
+            config
+                .find_inbox(name)
+                .context(format!("No inbox named '{name}' is configured"))?,
+        ),
+        None => config
+            .inboxes()
+            .into_iter()
+            .find(|inbox| inbox.path.join(item_name).exists()),
+    };
+
+    match inbox.filter(|inbox| inbox.path.join(item_name).exists()) {
+        Some(inbox) => {
+            let path = inbox.path.join(item_name);
+            Ok((inbox, path))
+        }
+        None => anyhow::bail!("Item '{item_name}' not found in Inbox"),
+    }
+}
+
+/// Pin, snooze or (with `None`) release an inbox item, without touching it.
+pub fn set_item_hold(
+    config: &Config,
+    inbox: &str,
+    name: &str,
+    path: &std::path::Path,
+    hold: Option<Hold>,
+) -> Result<()> {
+    let _lock = Lock::state(config)?;
+    let mut state = NotificationState::load().unwrap_or_default();
+    let identity = FileIdentity::of(name, path);
+    let previous = state.hold(inbox, &identity);
+
+    state.set_hold(inbox, &identity, hold);
+    let change = match hold {
+        Some(hold) => format!("mark '{name}' as {}", hold.describe()),
+        None => format!("lift the pin or snooze of '{name}'"),
+    };
+    state.save_unless_dry_run(config, &change)?;
+
+    if !config.dry_run {
+        match (hold, previous) {
+            (Some(Hold::Pinned), _) => {
+                println!("📌 Pinned '{name}' - scans will leave it alone until you unpin it")
+            }
+            (Some(hold), _) => println!("💤 '{name}' is {}", hold.describe()),
+            (None, Some(previous)) => {
+                println!("🔓 '{name}' is no longer {}", previous.describe())
+            }
+            (None, None) => println!("ℹ️  '{name}' was not pinned or snoozed"),
+        }
+    }
+    Ok(())
+}
+
+/// When a snooze given as `--for` or `--until` ends.
+pub fn snooze_end(duration: Option<&str>, until: Option<&str>) -> Result<DateTime<Utc>> {
+    let end = match until {
+        Some(date) => {
+            let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .context(format!("Invalid date '{date}', expected YYYY-MM-DD"))?;
+            date.and_hms_opt(0, 0, 0)
+                .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+                .context(format!("Invalid date '{date}'"))?
+                .with_timezone(&Utc)
+        }
+        None => Utc::now() + parse_duration(duration.unwrap_or(DEFAULT_SNOOZE))?,
+    };
+
+    if end <= Utc::now() {
+        anyhow::bail!(
+            "Cannot snooze until {}: that is not in the future",
+            end.format("%Y-%m-%d")
+        );
+    }
+    Ok(end)
+}
+
+pub fn snooze_item(
+    item_name: &str,
+    inbox_name: Option<&str>,
+    duration: Option<&str>,
+    until: Option<&str>,
+    dry_run: bool,
+) -> Result<()> {
+    let config = load_config(dry_run, OutputFormat::Text)?;
+    let (inbox, path) = find_inbox_item(&config, item_name, inbox_name)?;
+    let end = snooze_end(duration, until)?;
+    set_item_hold(
+        &config,
+        &inbox.name,
+        item_name,
+        &path,
+        Some(Hold::SnoozedUntil(end)),
+    )
+}
+
+pub fn pin_item(item_name: &str, inbox_name: Option<&str>, dry_run: bool) -> Result<()> {
+    let config = load_config(dry_run, OutputFormat::Text)?;
+    let (inbox, path) = find_inbox_item(&config, item_name, inbox_name)?;
+    set_item_hold(&config, &inbox.name, item_name, &path, Some(Hold::Pinned))
+}
+
+pub fn unpin_item(item_name: &str, inbox_name: Option<&str>, dry_run: bool) -> Result<()> {
+    let config = load_config(dry_run, OutputFormat::Text)?;
+    let (inbox, path) = find_inbox_item(&config, item_name, inbox_name)?;
+    set_item_hold(&config, &inbox.name, item_name, &path, None)
+}
+
+pub fn list_pins() -> Result<()> {
+    let config = load_config(false, OutputFormat::Text)?;
+    let state = NotificationState::load().unwrap_or_default();
+    let holds = state.holds();
+
+    if holds.is_empty() {
+        println!("✨ No pinned or snoozed items");
+        return Ok(());
+    }
+
+    for (inbox, item, hold) in holds {
+        let icon = match hold {
+            Hold::Pinned => "📌",
+            Hold::SnoozedUntil(_) => "💤",
+        };
+        let missing = match config.find_inbox(inbox) {
+            Some(config_inbox) if config_inbox.path.join(&item.identity.name).exists() => "",
+            _ => " (no longer in the inbox)",
+        };
+        println!(
+            "   {icon} \"{}\" in {inbox} - {}{missing}",
+            item.identity.name,
+            hold.describe()
+        );
+    }
+    Ok(())
+}
+
+pub fn resurrect_files(
+    pattern: Option<&str>,
+    options: &ResurrectOptions,
+    dry_run: bool,
+) -> Result<()> {
+    let config = load_config(dry_run, OutputFormat::Text)?;
     let graveyard = GraveyardManager::new(config);
-    graveyard.resurrect_files(pattern)
+    graveyard.resurrect_files(pattern, options)
 }
 
-pub fn search_graveyard(pattern: &str) -> Result<()> {
-    let config = Config::load_without_save()?;
+pub fn search_graveyard(query: &SearchArgs, output: OutputFormat) -> Result<()> {
+    let config = load_config(false, output)?;
+    let query = SearchQuery::from_args(query)?;
     let graveyard = GraveyardManager::new(config);
-    graveyard.search_files(pattern)
+    graveyard.search_files(&query)
 }
 
-pub fn auto_archive_eligible_files(note: Option<&str>) -> Result<()> {
-    let config = Config::load_without_save()?;
+pub fn auto_archive_eligible_files(
+    note: Option<&str>,
+    dry_run: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let config = load_config(dry_run, output)?;
     let scanner = Scanner::new(config.clone());
     let archiver = Archiver::new(config.clone());
 
     let auto_archive_items = scanner.scan_auto_archive_eligible()?;
 
     if auto_archive_items.is_empty() {
+        say(&config, "✨ No files eligible for auto-archiving found - all files are within their auto-archive thresholds!");
+        return print_records::<ArchiveResult>(config.output, &[]);
+    }
+
+    say(
+        &config,
+        format!(
+            "Found {} {} exceeding their auto-archive threshold:",
+            auto_archive_items.len(),
+            if auto_archive_items.len() == 1 {
+                "file"
+            } else {
+                "files"
+            }
+        ),
+    );
+
+    for item in &auto_archive_items {
+        let rule_info = match &item.policy.rule {
+            Some(rule) => format!(" [rule {rule}: {:?}]", item.policy.action),
+            None => String::new(),
+        };
+        say(
+            &config,
+            format!(
+                "   {} (threshold: {} days){}",
+                item.display(),
+                item.policy.auto_archive_threshold_days,
+                rule_info
+            ),
+        );
+    }
+
+    say(&config, "\n🤖 Auto-archiving these files...");
+    let mut archived_count = 0;
+    let mut deleted_count = 0;
+    let mut results = Vec::new();
+    for item in &auto_archive_items {
+        let mut result = ArchiveResult::new(item, item.policy.action, &config);
+        match item.policy.action {
+            RuleAction::Archive => {
+                result.archived = Some(archiver.archive_item_with_note(item, note)?);
+                archived_count += 1;
+            }
+            RuleAction::Delete => {
+                delete_stale_item(item, &config)?;
+                deleted_count += 1;
+            }
+            RuleAction::Keep => continue,
+        }
+        results.push(result);
+    }
+    print_records(config.output, &results)?;
+
+    if config.dry_run {
+        say(&config, format!(
+            "\n🔍 Dry run complete: {archived_count} would be archived, {deleted_count} would be deleted"
+        ));
+        return Ok(());
+    }
+
+    say(
+        &config,
+        format!(
+            "\n🎉 Successfully auto-archived {} {} to the Graveyard!",
+            archived_count,
+            if archived_count == 1 { "file" } else { "files" }
+        ),
+    );
+    if deleted_count > 0 {
+        say(
+            &config,
+            format!(
+                "🗑️  Deleted {} {} as configured by rules.",
+                deleted_count,
+                if deleted_count == 1 { "file" } else { "files" }
+            ),
+        );
+    }
+    Ok(())
+}
+
+pub fn reindex(dry_run: bool) -> Result<()> {
+    let config = load_config(dry_run, OutputFormat::Text)?;
+    let _lock = Lock::graveyard(&config)?;
+    let index = GraveyardIndex::build(&config)?;
+
+    if config.dry_run {
         println!(
-            "✨ No files eligible for auto-archiving found - all files are within the auto-archive threshold of {} days!",
-            config.auto_archive_threshold_days
+            "🔍 Would write {} entries to the Graveyard index",
+            index.entries.len()
         );
         return Ok(());
     }
 
+    index.save(&config)?;
     println!(
-        "Found {} {} exceeding the auto-archive threshold of {} days:",
-        auto_archive_items.len(),
-        if auto_archive_items.len() == 1 {
-            "file"
-        } else {
-            "files"
-        },
-        config.auto_archive_threshold_days
+        "✅ Indexed {} archived items in {}",
+        index.entries.len(),
+        config.graveyard.display()
     );
+    Ok(())
+}
 
-    for item in &auto_archive_items {
-        println!("   {}", item.display());
+pub fn fsck(output: OutputFormat) -> Result<()> {
+    let config = load_config(false, output)?;
+    let report = fsck::check(&config)?;
+
+    if !output.is_text() {
+        print_records(output, &report.issues)?;
+    } else {
+        for issue in &report.issues {
+            let icon = match issue.kind {
+                IssueKind::Missing => "❓",
+                IssueKind::Corrupted => "💥",
+                IssueKind::Orphaned => "👻",
+            };
+            println!(
+                "{icon} {:?}: {}\n   {}",
+                issue.kind,
+                issue.path.display(),
+                issue.detail
+            );
+        }
+
+        println!("\n✅ Checked {} archived items", report.checked);
+        if report.unverified > 0 {
+            println!(
+                "ℹ️  {} items were archived before checksums were recorded and could not be verified",
+                report.unverified
+            );
+        }
     }
 
-    println!("\n🤖 Auto-archiving these files...");
-    for item in &auto_archive_items {
-        archiver.archive_item_with_note(item, note)?;
+    if !report.issues.is_empty() {
+        anyhow::bail!("Found {} problems in the Graveyard", report.issues.len());
     }
+    if output.is_text() {
+        println!("🎉 No problems found");
+    }
+    Ok(())
+}
 
-    println!(
-        "\n🎉 Successfully auto-archived {} {} to the Graveyard!",
-        auto_archive_items.len(),
-        if auto_archive_items.len() == 1 {
-            "file"
-        } else {
-            "files"
+pub fn repair_graveyard(dry_run: bool) -> Result<()> {
+    let config = load_config(dry_run, OutputFormat::Text)?;
+    let report = repair::repair(&config)?;
+
+    let (relink, remove) = if config.dry_run {
+        ("🔍 Would re-point", "🔍 Would remove")
+    } else {
+        ("🔗 Re-pointed", "🧹 Removed")
+    };
+    for (link, original) in &report.relinked {
+        println!("{relink} {} -> {}", link.display(), original.display());
+    }
+    for link in &report.removed_links {
+        println!("{remove} dangling symlink {}", link.display());
+    }
+    for epitaph in &report.removed_epitaphs {
+        println!("{remove} orphaned epitaph {}", epitaph.display());
+    }
+
+    let total = report.relinked.len() + report.removed_links.len() + report.removed_epitaphs.len();
+    if total == 0 {
+        println!("✅ Nothing to repair");
+    } else {
+        println!(
+            "\n✅ {} symlinks re-pointed, {} dangling symlinks and {} orphaned epitaphs removed",
+            report.relinked.len(),
+            report.removed_links.len(),
+            report.removed_epitaphs.len()
+        );
+    }
+    Ok(())
+}
+
+pub fn migrate_graveyard(dry_run: bool) -> Result<()> {
+    let config = load_config(dry_run, OutputFormat::Text)?;
+    // Held from planning through applying, so the plan can't go stale
+    let _lock = Lock::graveyard(&config)?;
+    let migration = Migration::plan(&config)?;
+
+    if !migration.is_needed() {
+        println!("✅ The Graveyard already matches the configured layout");
+        return Ok(());
+    }
+    let moved: Vec<_> = migration
+        .items
+        .iter()
+        .filter(|item| !item.in_place())
+        .collect();
+    if !config.dry_run {
+        migration.apply(&config)?;
+    }
+
+    let verb = if config.dry_run {
+        "🔍 Would move"
+    } else {
+        "📦 Moved"
+    };
+    for item in &moved {
+        let originals: Vec<String> = item
+            .originals
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        println!(
+            "{verb} {} -> {}",
+            item.from[0].display(),
+            originals.join(", ")
+        );
+    }
+
+    if config.dry_run {
+        let unplanned = migration.unplanned(&config)?;
+        if !unplanned.is_empty() {
+            println!("\n⚠️  The migration would stop: these are not archived items known to relfa, and would be lost with the old layout:");
+            for path in &unplanned {
+                println!("   {}", path.display());
+            }
         }
-    );
+        println!(
+            "\n🔍 Would migrate {} of {} archived items into the configured layout",
+            moved.len(),
+            migration.items.len()
+        );
+    } else {
+        println!(
+            "\n✅ Migrated {} archived items into the configured layout",
+            moved.len()
+        );
+    }
+    Ok(())
+}
+
+pub fn undo(last: Option<usize>, id: Option<u64>, dry_run: bool) -> Result<()> {
+    let config = load_config(dry_run, OutputFormat::Text)?;
+    let _lock = Lock::graveyard(&config)?;
+    let journal = Journal::load()?;
+    let pending = journal.pending();
+
+    let entries: Vec<&JournalEntry> = match id {
+        Some(id) => match pending.iter().find(|entry| entry.id == id) {
+            Some(entry) => vec![*entry],
+            None => {
+                match journal.entries.iter().find(|entry| entry.id == id) {
+                    Some(JournalEntry {
+                        operation: Operation::Delete { name, .. },
+                        ..
+                    }) => println!(
+                        "❌ Journal entry {id} is the delete of '{name}', which was permanent and cannot be undone"
+                    ),
+                    Some(_) => {
+                        println!("❌ Journal entry {id} was already undone or is an undo itself")
+                    }
+                    None => println!("❌ No journal entry with id {id}"),
+                }
+                return Ok(());
+            }
+        },
+        None => pending.into_iter().take(last.unwrap_or(1)).collect(),
+    };
+
+    if entries.is_empty() {
+        println!("✨ Nothing left to undo in the journal!");
+        return Ok(());
+    }
+
+    // Deletes can't be undone, so they are passed over; say so for the ones
+    // recorded after the operations being undone
+    if id.is_none() {
+        let oldest = entries.iter().map(|entry| entry.id).min().unwrap_or(0);
+        for entry in journal.entries.iter().rev() {
+            if let Operation::Delete { name, .. } = &entry.operation {
+                if entry.id > oldest {
+                    println!(
+                        "⏭️  Skipping #{} delete of '{name}': permanently deleted items cannot be brought back",
+                        entry.id
+                    );
+                }
+            }
+        }
+    }
+
+    let archiver = Archiver::new(config.clone());
+    let mut undone_count = 0;
+
+    // Entries come newest first, so later operations are reverted before earlier ones
+    for entry in entries {
+        println!(
+            "\n⏪ Undoing #{} {} ({})",
+            entry.id,
+            entry.operation.describe(),
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+
+        let _state_lock = Lock::state(&config)?;
+        let mut state = NotificationState::load().unwrap_or_default();
+        match &entry.operation {
+            Operation::Archive {
+                item,
+                notification_count,
+                first_seen,
+                notified,
+            } => {
+                archiver.unarchive(item)?;
+                let identity = FileIdentity::of(&item.name, &item.original_path);
+                state.restore_notifications(
+                    &item.inbox,
+                    &identity,
+                    *notification_count,
+                    *first_seen,
+                    notified.clone(),
+                );
+                state.save_unless_dry_run(
+                    &config,
+                    &format!("restore notifications for '{}'", item.name),
+                )?;
+            }
+            Operation::Touch {
+                inbox,
+                name,
+                path,
+                previous_modified,
+                notification_count,
+                first_seen,
+                notified,
+            } => {
+                if config.dry_run {
+                    println!(
+                        "🔍 Would set modification time of '{}' back to {}",
+                        path.display(),
+                        previous_modified.format("%Y-%m-%d %H:%M:%S UTC")
+                    );
+                } else {
+                    let mtime = filetime::FileTime::from_system_time((*previous_modified).into());
+                    filetime::set_file_mtime(path, mtime).context(format!(
+                        "Failed to restore modification time for: {}",
+                        path.display()
+                    ))?;
+                    println!("↩️  Restored modification time of '{name}'");
+                }
+                state.restore_notifications(
+                    inbox,
+                    &FileIdentity::of(name, path),
+                    *notification_count,
+                    *first_seen,
+                    notified.clone(),
+                );
+                state
+                    .save_unless_dry_run(&config, &format!("restore notifications for '{name}'"))?;
+            }
+            Operation::Resurrect {
+                source,
+                destination,
+                moved: true,
+                compression,
+                epitaph,
+            } => {
+                // Put the item back where it was stored; symlink views are not recreated
+                if destination.symlink_metadata().is_err() {
+                    anyhow::bail!(
+                        "Cannot bury it again: {} no longer exists",
+                        destination.display()
+                    );
+                }
+                if source.symlink_metadata().is_ok() {
+                    anyhow::bail!("Cannot bury it again: {} is taken", source.display());
+                }
+                if config.dry_run {
+                    println!(
+                        "🔍 Would move '{}' back to {}",
+                        destination.display(),
+                        source.display()
+                    );
+                } else {
+                    if let Some(parent) = source.parent() {
+                        std::fs::create_dir_all(parent)
+                            .context("Failed to recreate Graveyard directory")?;
+                    }
+                    if let Some(compression) = compression {
+                        bundle::pack(destination, source, *compression)?;
+                        delete_item(destination)?;
+                    } else {
+                        move_item(destination, source)?;
+                    }
+                    if let Some(store) = ContentStore::new(&config) {
+                        store.absorb(source)?;
+                    }
+                    if let Some(epitaph) = epitaph {
+                        let mut epitaph = Epitaph::parse(epitaph);
+                        // A bundle packed anew is not byte-identical to the old one
+                        if compression.is_some() {
+                            epitaph.checksum = Some(item_hash(source)?);
+                        }
+                        std::fs::write(Epitaph::path_for(source), epitaph.serialize())
+                            .context("Failed to restore epitaph")?;
+                    }
+                    GraveyardIndex::append(
+                        &config,
+                        &[GraveyardIndex::entry_from_tree(&config, source)?],
+                    )?;
+                    println!(
+                        "🪦 Moved '{}' back to {}",
+                        destination.display(),
+                        source.display()
+                    );
+                }
+            }
+            Operation::Resurrect { destination, .. } => {
+                if destination.symlink_metadata().is_err() {
+                    println!("⚠️  {} no longer exists", destination.display());
+                } else if config.dry_run {
+                    println!("🔍 Would remove {}", destination.display());
+                } else {
+                    delete_item(destination)?;
+                    println!("🧹 Removed resurrected copy {}", destination.display());
+                }
+            }
+            // Left out of `Journal::pending`
+            Operation::Delete { .. } | Operation::Undo { .. } => continue,
+        }
+
+        Journal::record(&config, Operation::Undo { target: entry.id });
+        undone_count += 1;
+    }
+
+    if config.dry_run {
+        println!("\n🔍 Dry run complete: {undone_count} operations would be undone");
+    } else {
+        println!("\n🎉 Undid {undone_count} operations");
+    }
     Ok(())
 }
 
@@ -354,7 +1144,7 @@ pub fn show_config() -> Result<()> {
 
     let config = if config_path.exists() {
         // Config exists, try to load it and report any parsing errors
-        match Config::load_without_save() {
+        match Settings::load_without_save() {
             Ok(config) => config,
             Err(e) => {
                 eprintln!("❌ Error parsing config file at {}:", config_path.display());
@@ -366,7 +1156,7 @@ pub fn show_config() -> Result<()> {
         }
     } else {
         // Config doesn't exist, create it
-        Config::load()?
+        Settings::load()?
     };
     println!("{}", config.display());
     println!(
@@ -2,31 +2,277 @@ use anyhow::{Context, Result};
 use dirs::home_dir;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
 
+use crate::output::OutputFormat;
+use crate::template::Template;
+
+/// The settings of the config file, as loaded from and saved back to it.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Config {
+pub struct Settings {
     pub inbox: PathBuf,
     pub graveyard: PathBuf,
     pub age_threshold_days: u32,
     #[serde(default = "default_auto_archive_threshold")]
     pub auto_archive_threshold_days: u32,
-    #[serde(default = "default_auto_archive_min_scans")]
-    pub auto_archive_min_scans: u32,
+    /// Distinct days an item must have been notified on before auto-archiving
+    #[serde(default = "default_auto_archive_min_notified_days")]
+    pub auto_archive_min_notified_days: u32,
+    /// Days from the first of those notifications to the last
+    #[serde(default = "default_auto_archive_min_notified_span_days")]
+    pub auto_archive_min_notified_span_days: u32,
+    /// Replaced by the two settings above; `0` still means not to wait
+    #[serde(default, skip_serializing)]
+    auto_archive_min_scans: Option<u32>,
     pub notification: NotificationType,
     pub path_format: PathFormatConfig,
     #[serde(default = "default_pager")]
     pub pager: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inboxes: Vec<InboxConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RuleConfig>,
+    #[serde(default, skip_serializing_if = "DedupMode::is_off")]
+    pub dedup: DedupMode,
+    /// Bundle format for archived folders, and for files above `compress_larger_than`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compress: Option<Compression>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compress_larger_than: Option<String>,
+}
+
+/// The settings together with the command-line flags of the current run,
+/// which are never written back to the config file.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub settings: Settings,
+    /// Set from `--dry-run`: report planned changes instead of making them
+    pub dry_run: bool,
+    /// Set from `--format`: how results are written to stdout
+    pub output: OutputFormat,
+}
+
+impl Config {
+    pub fn new(settings: Settings, dry_run: bool, output: OutputFormat) -> Self {
+        Self {
+            settings,
+            dry_run,
+            output,
+        }
+    }
+}
+
+impl Deref for Config {
+    type Target = Settings;
+
+    fn deref(&self) -> &Settings {
+        &self.settings
+    }
+}
+
+impl DerefMut for Config {
+    fn deref_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
 }
 
+/// An additional inbox to monitor. Thresholds left unset fall back to the
+/// top-level values.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InboxConfig {
+    pub name: String,
+    pub path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age_threshold_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_archive_threshold_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_archive_min_notified_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_archive_min_notified_span_days: Option<u32>,
+    #[serde(default, skip_serializing)]
+    auto_archive_min_scans: Option<u32>,
+}
+
+/// A monitored inbox with all of its thresholds resolved.
+#[derive(Debug, Clone)]
+pub struct Inbox {
+    pub name: String,
+    pub path: PathBuf,
+    pub age_threshold_days: u32,
+    pub auto_archive_threshold_days: u32,
+    pub auto_archive_min_notified_days: u32,
+    pub auto_archive_min_notified_span_days: u32,
+}
+
+/// A per-pattern archival policy. An entry matches when every condition that
+/// is set matches; the first matching rule wins and its thresholds override
+/// those of the entry's inbox.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RuleConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub larger_than: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smaller_than: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age_threshold_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_archive_threshold_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_archive_min_notified_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_archive_min_notified_span_days: Option<u32>,
+    #[serde(default, skip_serializing)]
+    auto_archive_min_scans: Option<u32>,
+    #[serde(default)]
+    pub action: RuleAction,
+    /// Bundle format for every matching item, overriding the global `compress`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compress: Option<Compression>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    /// Move the item to the Graveyard once it exceeds the auto-archive threshold
+    #[default]
+    Archive,
+    /// Permanently delete the item once it exceeds the auto-archive threshold
+    Delete,
+    /// Never archive or delete the item automatically
+    Keep,
+}
+
+/// How identical files share a single copy in the Graveyard's content store.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupMode {
+    /// Every archived file is stored as-is
+    #[default]
+    Off,
+    /// Stored files are hardlinks into the content store
+    Hardlink,
+    /// Stored files are symlinks into the content store
+    Symlink,
+}
+
+impl DedupMode {
+    pub fn is_off(&self) -> bool {
+        *self == DedupMode::Off
+    }
+}
+
+/// Format of the tar bundles compressed items are stored as.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// Store items as they are
+    None,
+    /// `.tar.gz`
+    Gzip,
+    /// `.tar.zst`
+    Zstd,
+}
+
+impl Compression {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Compression::None => "",
+            Compression::Gzip => "tar.gz",
+            Compression::Zstd => "tar.zst",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "none" => Some(Compression::None),
+            "gzip" => Some(Compression::Gzip),
+            "zstd" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+/// Name under which the top-level `inbox` is known.
+pub const DEFAULT_INBOX_NAME: &str = "inbox";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PathFormatConfig {
     pub created_subdir: SubdirConfig,
     pub modified_subdir: SubdirConfig,
     pub archived_subdir: SubdirConfig,
     pub date_format: String,
+    /// Further views, each filing items under its own template
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub views: Vec<ViewConfig>,
 }
 
+/// A user-defined view of the Graveyard, such as `by-type/{ext}`. Like the
+/// built-in subdirs, it stores originals, symlinks to another view, or nothing.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ViewConfig {
+    #[serde(flatten)]
+    pub subdir: SubdirConfig,
+    /// Path inside the view, with the same placeholders as `date_format`
+    pub template: String,
+    /// The timestamp the date placeholders of `template` refer to
+    #[serde(default)]
+    pub date: DateField,
+}
+
+/// Which of an item's timestamps a view files it by.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DateField {
+    Created,
+    Modified,
+    #[default]
+    Archived,
+}
+
+/// A subdir of the Graveyard and the template items are filed under inside it.
+#[derive(Debug, Clone, Copy)]
+pub struct View<'a> {
+    pub subdir: &'a SubdirConfig,
+    pub template: &'a str,
+    pub date: DateField,
+}
+
+/// What view templates are filled in from.
+#[derive(Debug, Clone)]
+pub struct ViewItem {
+    /// Name of the item in its inbox
+    pub name: String,
+    pub is_directory: bool,
+    pub inbox: Option<String>,
+    pub hostname: String,
+    pub created: chrono::DateTime<chrono::Utc>,
+    pub modified: chrono::DateTime<chrono::Utc>,
+    pub archived: chrono::DateTime<chrono::Utc>,
+}
+
+/// Stands in for template values an item does not have.
+const UNKNOWN_VIEW_VALUE: &str = "other";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum SubdirConfig {
@@ -53,11 +299,15 @@ fn default_auto_archive_threshold() -> u32 {
     7
 }
 
-fn default_auto_archive_min_scans() -> u32 {
-    24
+fn default_auto_archive_min_notified_days() -> u32 {
+    3
+}
+
+fn default_auto_archive_min_notified_span_days() -> u32 {
+    3
 }
 
-impl Default for Config {
+impl Default for Settings {
     fn default() -> Self {
         let home = home_dir().unwrap_or_else(|| PathBuf::from("."));
 
@@ -66,10 +316,18 @@ impl Default for Config {
             graveyard: home.join("Graveyard"),
             age_threshold_days: 3,
             auto_archive_threshold_days: default_auto_archive_threshold(),
-            auto_archive_min_scans: default_auto_archive_min_scans(),
+            auto_archive_min_notified_days: default_auto_archive_min_notified_days(),
+            auto_archive_min_notified_span_days: default_auto_archive_min_notified_span_days(),
+            auto_archive_min_scans: None,
             notification: NotificationType::Cli,
             path_format: PathFormatConfig::default(),
             pager: default_pager(),
+            inboxes: Vec::new(),
+            ignore: Vec::new(),
+            rules: Vec::new(),
+            dedup: DedupMode::Off,
+            compress: None,
+            compress_larger_than: None,
         }
     }
 }
@@ -89,10 +347,100 @@ impl Default for PathFormatConfig {
                 target: "created".to_string(),
             },
             date_format: "{hostname}/{year}/{month:02}/{day:02}".to_string(),
+            views: Vec::new(),
         }
     }
 }
 
+impl PathFormatConfig {
+    /// The subdir the archiver moves items into: the first one storing originals.
+    pub fn primary_subdir(&self) -> Option<&str> {
+        self.views()
+            .into_iter()
+            .find(|view| view.subdir.is_original())
+            .and_then(|view| view.subdir.get_name())
+    }
+
+    /// Every configured view, starting with the built-in created, modified and
+    /// archived subdirs, which are filed under `date_format`.
+    pub fn views(&self) -> Vec<View<'_>> {
+        let mut views = vec![
+            View {
+                subdir: &self.created_subdir,
+                template: &self.date_format,
+                date: DateField::Created,
+            },
+            View {
+                subdir: &self.modified_subdir,
+                template: &self.date_format,
+                date: DateField::Modified,
+            },
+            View {
+                subdir: &self.archived_subdir,
+                template: &self.date_format,
+                date: DateField::Archived,
+            },
+        ];
+        views.extend(self.views.iter().map(|view| View {
+            subdir: &view.subdir,
+            template: &view.template,
+            date: view.date,
+        }));
+        views
+    }
+
+    /// The enabled view whose subdir is called `name`.
+    pub fn view(&self, name: &str) -> Option<View<'_>> {
+        self.views()
+            .into_iter()
+            .find(|view| view.subdir.get_name() == Some(name))
+    }
+}
+
+impl View<'_> {
+    /// Whether the template files items by date at all.
+    pub fn is_dated(&self) -> bool {
+        // Templates are checked when the config is loaded
+        Template::parse(self.template).is_ok_and(|template| template.uses_date())
+    }
+}
+
+impl ViewItem {
+    pub fn time(&self, field: DateField) -> chrono::DateTime<chrono::Utc> {
+        match field {
+            DateField::Created => self.created,
+            DateField::Modified => self.modified,
+            DateField::Archived => self.archived,
+        }
+    }
+
+    /// Lowercase extension, `folder` for folders.
+    pub fn extension(&self) -> String {
+        if self.is_directory {
+            return "folder".to_string();
+        }
+        Path::new(&self.name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| UNKNOWN_VIEW_VALUE.to_string())
+    }
+
+    /// Top-level MIME type guessed from the name, such as `image` or `text`.
+    pub fn mime_category(&self) -> String {
+        if self.is_directory {
+            return "folder".to_string();
+        }
+        mime_guess::from_path(&self.name)
+            .first()
+            .map(|mime| mime.type_().to_string())
+            .unwrap_or_else(|| UNKNOWN_VIEW_VALUE.to_string())
+    }
+
+    pub fn inbox(&self) -> &str {
+        self.inbox.as_deref().unwrap_or(UNKNOWN_VIEW_VALUE)
+    }
+}
+
 impl SubdirConfig {
     pub fn display(&self) -> String {
         match self {
@@ -125,7 +473,36 @@ impl SubdirConfig {
     }
 }
 
-impl Config {
+impl RuleConfig {
+    pub fn display(&self) -> String {
+        let mut conditions = Vec::new();
+        if let Some(glob) = &self.glob {
+            conditions.push(format!("glob {glob}"));
+        }
+        if !self.extensions.is_empty() {
+            conditions.push(format!("extension {}", self.extensions.join("|")));
+        }
+        if let Some(size) = &self.larger_than {
+            conditions.push(format!("larger than {size}"));
+        }
+        if let Some(size) = &self.smaller_than {
+            conditions.push(format!("smaller than {size}"));
+        }
+        if let Some(mime) = &self.mime {
+            conditions.push(format!("mime {mime}"));
+        }
+        if conditions.is_empty() {
+            conditions.push("everything".to_string());
+        }
+
+        match &self.name {
+            Some(name) => format!("{name} ({})", conditions.join(", ")),
+            None => conditions.join(", "),
+        }
+    }
+}
+
+impl Settings {
     pub fn load() -> Result<Self> {
         Self::load_with_save(true)
     }
@@ -140,21 +517,15 @@ impl Config {
         if config_path.exists() {
             let content = fs::read_to_string(&config_path).context("Failed to read config file")?;
 
-            // Try to parse, but if it fails due to missing fields, use defaults
-            match toml::from_str::<Self>(&content) {
-                Ok(config) => Ok(config),
-                Err(e) => {
-                    if auto_save {
-                        // Only fall back to defaults if we're allowed to save (non-display mode)
-                        let config = Self::default();
-                        config.save()?;
-                        Ok(config)
-                    } else {
-                        // In display mode, report the actual parsing error
-                        Err(anyhow::anyhow!("Failed to parse config file: {}", e))
-                    }
-                }
-            }
+            // Never fall back to defaults here: saving them would wipe the
+            // user's inboxes and rules over a single typo
+            let mut config = toml::from_str::<Self>(&content).context(format!(
+                "Failed to parse config file {}",
+                config_path.display()
+            ))?;
+            config.upgrade_min_scans();
+            config.validate()?;
+            Ok(config)
         } else {
             let config = Self::default();
             if auto_save {
@@ -164,6 +535,69 @@ impl Config {
         }
     }
 
+    /// `auto_archive_min_scans` counted scans, however close together they
+    /// ran. Only its "don't wait" value of `0` carries over to the
+    /// notified-days settings.
+    fn upgrade_min_scans(&mut self) {
+        if self.auto_archive_min_scans.take() == Some(0) {
+            self.auto_archive_min_notified_days = 0;
+            self.auto_archive_min_notified_span_days = 0;
+        }
+        let overrides = self
+            .inboxes
+            .iter_mut()
+            .map(|inbox| {
+                (
+                    inbox.auto_archive_min_scans.take(),
+                    &mut inbox.auto_archive_min_notified_days,
+                    &mut inbox.auto_archive_min_notified_span_days,
+                )
+            })
+            .chain(self.rules.iter_mut().map(|rule| {
+                (
+                    rule.auto_archive_min_scans.take(),
+                    &mut rule.auto_archive_min_notified_days,
+                    &mut rule.auto_archive_min_notified_span_days,
+                )
+            }));
+        for (min_scans, days, span_days) in overrides {
+            if min_scans == Some(0) {
+                days.get_or_insert(0);
+                span_days.get_or_insert(0);
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        let mut names = std::collections::HashSet::new();
+        names.insert(DEFAULT_INBOX_NAME);
+
+        for inbox in &self.inboxes {
+            if !names.insert(inbox.name.as_str()) {
+                return Err(anyhow::anyhow!(
+                    "Duplicate inbox name '{}' in config ('{}' is reserved for the top-level inbox)",
+                    inbox.name,
+                    DEFAULT_INBOX_NAME
+                ));
+            }
+        }
+
+        crate::rules::RuleSet::new(self)?;
+
+        Template::parse(&self.path_format.date_format)
+            .map_err(|e| anyhow::anyhow!("Invalid path_format.date_format: {e}"))?;
+        for view in &self.path_format.views {
+            Template::parse(&view.template).map_err(|e| {
+                anyhow::anyhow!(
+                    "Invalid template for view '{}': {e}",
+                    view.subdir.get_name().unwrap_or("disabled")
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+
     pub fn save(&self) -> Result<()> {
         let config_path = Self::config_path();
 
@@ -186,6 +620,41 @@ impl Config {
         }
     }
 
+    /// All monitored inboxes, starting with the top-level `inbox`.
+    pub fn inboxes(&self) -> Vec<Inbox> {
+        let mut inboxes = vec![Inbox {
+            name: DEFAULT_INBOX_NAME.to_string(),
+            path: self.inbox.clone(),
+            age_threshold_days: self.age_threshold_days,
+            auto_archive_threshold_days: self.auto_archive_threshold_days,
+            auto_archive_min_notified_days: self.auto_archive_min_notified_days,
+            auto_archive_min_notified_span_days: self.auto_archive_min_notified_span_days,
+        }];
+
+        for inbox in &self.inboxes {
+            inboxes.push(Inbox {
+                name: inbox.name.clone(),
+                path: inbox.path.clone(),
+                age_threshold_days: inbox.age_threshold_days.unwrap_or(self.age_threshold_days),
+                auto_archive_threshold_days: inbox
+                    .auto_archive_threshold_days
+                    .unwrap_or(self.auto_archive_threshold_days),
+                auto_archive_min_notified_days: inbox
+                    .auto_archive_min_notified_days
+                    .unwrap_or(self.auto_archive_min_notified_days),
+                auto_archive_min_notified_span_days: inbox
+                    .auto_archive_min_notified_span_days
+                    .unwrap_or(self.auto_archive_min_notified_span_days),
+            });
+        }
+
+        inboxes
+    }
+
+    pub fn find_inbox(&self, name: &str) -> Option<Inbox> {
+        self.inboxes().into_iter().find(|inbox| inbox.name == name)
+    }
+
     pub fn get_hostname(&self) -> String {
         gethostname::gethostname()
             .to_string_lossy()
@@ -194,34 +663,108 @@ impl Config {
             .collect()
     }
 
-    pub fn format_date_path(&self, date: &chrono::DateTime<chrono::Utc>) -> String {
-        use chrono::Datelike;
+    /// Where `item` is filed inside `view`, relative to the view's subdir.
+    pub fn format_view_path(&self, view: &View, item: &ViewItem) -> String {
+        render_template(view.template, item, &item.time(view.date))
+    }
 
-        self.path_format
-            .date_format
-            .replace("{hostname}", &self.get_hostname())
-            .replace("{year}", &date.year().to_string())
-            .replace("{month:02}", &format!("{:02}", date.month()))
-            .replace("{day:02}", &format!("{:02}", date.day()))
-            .replace("{month}", &date.month().to_string())
-            .replace("{day}", &date.day().to_string())
+    /// Full path of `file_name` filed for `item` inside `view`.
+    pub fn view_location(&self, view: &View, item: &ViewItem, file_name: &str) -> Option<PathBuf> {
+        Some(
+            self.graveyard
+                .join(view.subdir.get_name()?)
+                .join(self.format_view_path(view, item))
+                .join(file_name),
+        )
     }
 
     pub fn display(&self) -> String {
+        let mut inboxes = String::new();
+        for inbox in &self.inboxes {
+            inboxes.push_str(&format!(
+                "\n   {}: {} (stale after {} days, auto-archive after {} days / notified on {} days over {})",
+                inbox.name,
+                inbox.path.display(),
+                inbox.age_threshold_days.unwrap_or(self.age_threshold_days),
+                inbox
+                    .auto_archive_threshold_days
+                    .unwrap_or(self.auto_archive_threshold_days),
+                inbox
+                    .auto_archive_min_notified_days
+                    .unwrap_or(self.auto_archive_min_notified_days),
+                inbox
+                    .auto_archive_min_notified_span_days
+                    .unwrap_or(self.auto_archive_min_notified_span_days)
+            ));
+        }
+        if !inboxes.is_empty() {
+            inboxes = format!("\n📥 Additional inboxes:{inboxes}");
+        }
+        let mut rules = String::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            rules.push_str(&format!(
+                "\n   {}. {} -> {:?}",
+                i + 1,
+                rule.display(),
+                rule.action
+            ));
+        }
+        if !rules.is_empty() {
+            rules = format!("\n📏 Rules:{rules}");
+        }
+        let ignore = if self.ignore.is_empty() {
+            "none".to_string()
+        } else {
+            self.ignore.join(", ")
+        };
+        let views: String = self
+            .path_format
+            .views
+            .iter()
+            .map(|view| format!("\n   {}: {}", view.subdir.display(), view.template))
+            .collect();
+        let compress = match (self.compress, &self.compress_larger_than) {
+            (None | Some(Compression::None), _) => "off".to_string(),
+            (Some(format), Some(size)) => {
+                format!("{} (folders, files larger than {size})", format.name())
+            }
+            (Some(format), None) => format!("{} (folders)", format.name()),
+        };
+
         format!(
-            "📂 Inbox: {}\n🪦 Graveyard: {}\n⏰ Age threshold: {} days\n🤖 Auto-archive threshold: {} days\n🔄 Auto-archive min scans: {}\n🖥️  Hostname: {}\n🔔 Notifications: {:?}\n📄 Pager: {}\n📁 Path format:\n   Created: {}\n   Modified: {}\n   Archived: {}\n   Date format: {}",
+            "📂 Inbox: {}{}\n🪦 Graveyard: {}\n🙈 Ignored: {}\n⏰ Age threshold: {} days\n🤖 Auto-archive threshold: {} days\n🔄 Auto-archive after notifying on: {} days over at least {} days{}\n♻️  Deduplication: {:?}\n🗜️  Compression: {}\n🖥️  Hostname: {}\n🔔 Notifications: {:?}\n📄 Pager: {}\n📁 Path format:\n   Created: {}\n   Modified: {}\n   Archived: {}\n   Date format: {}{}",
             self.inbox.display(),
+            inboxes,
             self.graveyard.display(),
+            ignore,
             self.age_threshold_days,
             self.auto_archive_threshold_days,
-            self.auto_archive_min_scans,
+            self.auto_archive_min_notified_days,
+            self.auto_archive_min_notified_span_days,
+            rules,
+            self.dedup,
+            compress,
             self.get_hostname(),
             self.notification,
             self.pager,
             self.path_format.created_subdir.display(),
             self.path_format.modified_subdir.display(),
             self.path_format.archived_subdir.display(),
-            self.path_format.date_format
+            self.path_format.date_format,
+            views
         )
     }
 }
+
+/// `template` filled in for `item`. Templates are checked when the config is
+/// loaded, so one that fails to parse is used as is.
+fn render_template(
+    template: &str,
+    item: &ViewItem,
+    date: &chrono::DateTime<chrono::Utc>,
+) -> String {
+    match Template::parse(template) {
+        Ok(template) => template.render(item, date),
+        Err(_) => template.to_string(),
+    }
+}
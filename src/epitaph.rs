@@ -0,0 +1,243 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bundle;
+use crate::config::{Compression, Config, DateField, ViewItem};
+
+pub const EPITAPH_EXTENSION: &str = "epitaph";
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S UTC";
+
+/// The note stored next to every archived item, with a header describing it.
+///
+/// On disk it looks like:
+///
+/// ```text
+/// # Epitaph for report.pdf
+/// # Archived: 2025-08-09 10:00:00 UTC
+/// # Created: 2025-07-01 09:30:00 UTC
+/// # Modified: 2025-07-02 17:45:00 UTC
+/// # Hostname: laptop
+/// # Inbox: downloads
+/// # Compressed: zstd
+/// # SHA256: 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08
+///
+/// Free-form note
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Epitaph {
+    pub name: String,
+    pub archived: Option<DateTime<Utc>>,
+    pub created: Option<DateTime<Utc>>,
+    pub modified: Option<DateTime<Utc>>,
+    pub hostname: Option<String>,
+    pub inbox: Option<String>,
+    /// Set when the item is stored as a bundle that has to be unpacked
+    pub compressed: Option<Compression>,
+    /// Hash of the stored item when it was archived, as computed by `item_hash`
+    pub checksum: Option<String>,
+    /// Header fields this version does not know about, kept in order
+    pub extra: Vec<(String, String)>,
+    pub note: String,
+}
+
+impl Epitaph {
+    /// Location of the epitaph for an item stored at `item_path`: a sibling
+    /// named `<name>.epitaph`, for files and directories alike.
+    pub fn path_for(item_path: &Path) -> PathBuf {
+        let name = item_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        item_path.with_file_name(format!("{name}.{EPITAPH_EXTENSION}"))
+    }
+
+    pub fn is_epitaph_path(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|extension| extension == EPITAPH_EXTENSION)
+    }
+
+    /// Read the epitaph of the item stored at `item_path`, if it has one.
+    pub fn load_for(item_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path_for(item_path);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path)
+            .context(format!("Failed to read epitaph: {}", path.display()))?;
+        Ok(Some(Self::parse(&content)))
+    }
+
+    /// Parse an epitaph. Files without a header are treated as a bare note.
+    pub fn parse(content: &str) -> Self {
+        let mut epitaph = Self::default();
+
+        if !content.starts_with("# ") {
+            epitaph.note = content.trim().to_string();
+            return epitaph;
+        }
+
+        let (header, note) = content.split_once("\n\n").unwrap_or((content, ""));
+        epitaph.note = note.trim().to_string();
+
+        for line in header.lines() {
+            let Some(line) = line.strip_prefix("# ") else {
+                continue;
+            };
+
+            if let Some(name) = line.strip_prefix("Epitaph for ") {
+                epitaph.name = name.to_string();
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once(": ") else {
+                continue;
+            };
+            let value = value.trim();
+            match key {
+                "Archived" => epitaph.archived = parse_timestamp(value),
+                "Created" => epitaph.created = parse_timestamp(value),
+                "Modified" => epitaph.modified = parse_timestamp(value),
+                "Hostname" => epitaph.hostname = Some(value.to_string()),
+                "Inbox" => epitaph.inbox = Some(value.to_string()),
+                "Compressed" => epitaph.compressed = Compression::parse(value),
+                "SHA256" => epitaph.checksum = Some(value.to_string()),
+                _ => epitaph.extra.push((key.to_string(), value.to_string())),
+            }
+        }
+
+        epitaph
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut content = format!("# Epitaph for {}\n", self.name);
+
+        let timestamps = [
+            ("Archived", self.archived),
+            ("Created", self.created),
+            ("Modified", self.modified),
+        ];
+        for (key, time) in timestamps {
+            if let Some(time) = time {
+                content.push_str(&format!("# {key}: {}\n", time.format(TIMESTAMP_FORMAT)));
+            }
+        }
+        if let Some(hostname) = &self.hostname {
+            content.push_str(&format!("# Hostname: {hostname}\n"));
+        }
+        if let Some(inbox) = &self.inbox {
+            content.push_str(&format!("# Inbox: {inbox}\n"));
+        }
+        if let Some(compressed) = &self.compressed {
+            content.push_str(&format!("# Compressed: {}\n", compressed.name()));
+        }
+        if let Some(checksum) = &self.checksum {
+            content.push_str(&format!("# SHA256: {checksum}\n"));
+        }
+        for (key, value) in &self.extra {
+            content.push_str(&format!("# {key}: {value}\n"));
+        }
+
+        content.push('\n');
+        content.push_str(&self.note);
+        content
+    }
+
+    pub fn time(&self, field: DateField) -> Option<DateTime<Utc>> {
+        match field {
+            DateField::Created => self.created,
+            DateField::Modified => self.modified,
+            DateField::Archived => self.archived,
+        }
+    }
+
+    /// What views file the item stored at `item_path` by. Timestamps the
+    /// epitaph does not know fall back to the item's modification time.
+    pub fn view_item(&self, config: &Config, item_path: &Path) -> Result<ViewItem> {
+        let stored_name = item_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let is_directory = match self.compressed {
+            Some(compression) => bundle::contents(item_path, compression)?.is_directory,
+            None => item_path.is_dir(),
+        };
+        let modified = match self.modified {
+            Some(modified) => modified,
+            None => DateTime::from(fs::symlink_metadata(item_path)?.modified()?),
+        };
+
+        Ok(ViewItem {
+            name: if self.name.is_empty() {
+                stored_name
+            } else {
+                self.name.clone()
+            },
+            is_directory,
+            inbox: self.inbox.clone(),
+            hostname: self
+                .hostname
+                .clone()
+                .unwrap_or_else(|| config.get_hostname()),
+            created: self.created.unwrap_or(modified),
+            modified,
+            archived: self.archived.unwrap_or(modified),
+        })
+    }
+
+    /// The note, or `None` when nothing was written.
+    pub fn note(&self) -> Option<&str> {
+        Some(self.note.trim()).filter(|note| !note.is_empty())
+    }
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, TIMESTAMP_FORMAT)
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn round_trips_every_field() {
+        let epitaph = Epitaph {
+            name: "report.pdf".to_string(),
+            archived: Some(Utc.with_ymd_and_hms(2025, 8, 9, 10, 0, 0).unwrap()),
+            created: Some(Utc.with_ymd_and_hms(2025, 7, 1, 9, 30, 0).unwrap()),
+            modified: Some(Utc.with_ymd_and_hms(2025, 7, 2, 17, 45, 0).unwrap()),
+            hostname: Some("laptop".to_string()),
+            inbox: Some("downloads".to_string()),
+            compressed: Some(Compression::Zstd),
+            checksum: Some("9f86d081".to_string()),
+            extra: vec![("Reviewed-By".to_string(), "me".to_string())],
+            note: "Tax papers\n\nKeep until 2030".to_string(),
+        };
+
+        assert_eq!(Epitaph::parse(&epitaph.serialize()), epitaph);
+    }
+
+    #[test]
+    fn round_trips_an_empty_note() {
+        let epitaph = Epitaph {
+            name: "notes.txt".to_string(),
+            ..Epitaph::default()
+        };
+
+        assert_eq!(Epitaph::parse(&epitaph.serialize()), epitaph);
+    }
+
+    #[test]
+    fn reads_a_file_without_header_as_a_note() {
+        let epitaph = Epitaph::parse("Just a note\n");
+
+        assert_eq!(epitaph.note, "Just a note");
+        assert_eq!(epitaph.name, "");
+        assert_eq!(epitaph.archived, None);
+    }
+}
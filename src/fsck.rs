@@ -0,0 +1,162 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use walkdir::WalkDir;
+
+use crate::config::Config;
+use crate::epitaph::Epitaph;
+use crate::index::GraveyardIndex;
+use crate::store::ContentStore;
+use crate::utils::{item_hash, symlink_target};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueKind {
+    /// Something the index or a symlink view refers to is gone
+    Missing,
+    /// Content no longer matches its recorded checksum, or cannot be read
+    Corrupted,
+    /// Something nothing else accounts for
+    Orphaned,
+}
+
+/// A single problem found in the Graveyard.
+#[derive(Debug, Serialize)]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub path: PathBuf,
+    pub detail: String,
+}
+
+/// What a Graveyard check looked at and what it found.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Stored items that were re-hashed
+    pub checked: usize,
+    /// Items archived before checksums were recorded
+    pub unverified: usize,
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    fn push(&mut self, kind: IssueKind, path: PathBuf, detail: impl Into<String>) {
+        self.issues.push(Issue {
+            kind,
+            path,
+            detail: detail.into(),
+        });
+    }
+}
+
+/// Re-hash every stored item against its epitaph checksum and cross-check
+/// the tree, the index, the symlink views and the content store.
+pub fn check(config: &Config) -> Result<Report> {
+    let mut report = Report::default();
+    if !config.graveyard.exists() {
+        return Ok(report);
+    }
+
+    let items = GraveyardIndex::item_paths(config)?;
+    for path in &items {
+        report.checked += 1;
+        let epitaph = match Epitaph::load_for(path) {
+            Ok(epitaph) => epitaph.unwrap_or_default(),
+            Err(e) => {
+                report.push(IssueKind::Corrupted, path.clone(), format!("{e:#}"));
+                continue;
+            }
+        };
+        let Some(expected) = epitaph.checksum else {
+            report.unverified += 1;
+            continue;
+        };
+        match item_hash(path) {
+            Ok(actual) if actual == expected => {}
+            Ok(actual) => report.push(
+                IssueKind::Corrupted,
+                path.clone(),
+                format!("checksum is {actual}, expected {expected}"),
+            ),
+            Err(e) => report.push(IssueKind::Corrupted, path.clone(), format!("{e:#}")),
+        }
+    }
+
+    // The index and the tree should describe the same items
+    if let Some(index) = GraveyardIndex::load(config)? {
+        let indexed: HashSet<PathBuf> = index
+            .entries
+            .iter()
+            .map(|entry| config.graveyard.join(&entry.path))
+            .collect();
+        for path in &indexed {
+            if path.symlink_metadata().is_err() {
+                report.push(
+                    IssueKind::Missing,
+                    path.clone(),
+                    "listed in the index but not in the Graveyard",
+                );
+            }
+        }
+        for path in items.iter().filter(|path| !indexed.contains(*path)) {
+            report.push(
+                IssueKind::Orphaned,
+                path.clone(),
+                "not in the index; run `relfa reindex`",
+            );
+        }
+    }
+
+    let store = ContentStore::root(config);
+    for entry in WalkDir::new(&config.graveyard)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.path() != store)
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        if entry.path_is_symlink() {
+            // Dangling store links are already reported as unreadable items
+            if !path.exists() && !ContentStore::is_store_link(config, path) {
+                let target = symlink_target(path).unwrap_or_default();
+                report.push(
+                    IssueKind::Missing,
+                    path.to_path_buf(),
+                    format!("symlink points to missing {}", target.display()),
+                );
+            }
+        } else if Epitaph::is_epitaph_path(path) {
+            let item = path.with_extension("");
+            if item.symlink_metadata().is_err() {
+                report.push(
+                    IssueKind::Orphaned,
+                    path.to_path_buf(),
+                    "epitaph without an archived item",
+                );
+            }
+        }
+    }
+
+    for blob in ContentStore::blobs(config) {
+        let name = blob.file_name().unwrap_or_default().to_string_lossy();
+        match item_hash(&blob) {
+            Ok(actual) if actual == name => {}
+            Ok(actual) => report.push(
+                IssueKind::Corrupted,
+                blob.clone(),
+                format!("content store blob hashes to {actual}"),
+            ),
+            Err(e) => report.push(IssueKind::Corrupted, blob.clone(), format!("{e:#}")),
+        }
+    }
+    for blob in ContentStore::unreferenced_blobs(config)? {
+        report.push(
+            IssueKind::Orphaned,
+            blob,
+            "content store blob no archived file uses",
+        );
+    }
+
+    report.issues.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(report)
+}
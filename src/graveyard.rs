@@ -1,10 +1,91 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+use crate::bundle;
 use crate::config::Config;
-use crate::utils::copy_file_or_dir;
+use crate::epitaph::Epitaph;
+use crate::index::{GraveyardIndex, IndexEntry};
+use crate::journal::{Journal, Operation};
+use crate::lock::Lock;
+use crate::output::print_records;
+use crate::query::SearchQuery;
+use crate::store::ContentStore;
+use crate::utils::{
+    canonical_location, copy_file_or_dir, copy_times, delete_item, move_item, numbered_name,
+    remove_dir_with_permissions, remove_empty_parents, symlink_target,
+};
+
+/// A Graveyard entry matching a search, with its epitaph note if any.
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub id: String,
+    pub path: PathBuf,
+    pub relative_path: PathBuf,
+    pub host: Option<String>,
+    pub archived: Option<DateTime<Utc>>,
+    pub modified: Option<DateTime<Utc>>,
+    pub size: u64,
+    pub is_directory: bool,
+    pub note: Option<String>,
+    /// Whether the pattern matched the epitaph note rather than the name
+    pub matched_epitaph: bool,
+    /// Paths inside a compressed folder that matched the pattern
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<String>,
+}
+
+/// What to do when a resurrected item's destination already exists.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ConflictPolicy {
+    /// Pick a free name such as `report_1.pdf`
+    Rename,
+    Skip,
+    Overwrite,
+    /// Prompt for one of the above
+    Ask,
+}
+
+/// Which of several matches to resurrect.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    /// Ask, unless there is a single match
+    Prompt,
+    /// 1-based position in the listed matches
+    Index(usize),
+    Latest,
+    All,
+    /// Archive id, or a unique prefix of it
+    Id(String),
+}
+
+pub struct ResurrectOptions {
+    pub selection: Selection,
+    /// Fail instead of prompting
+    pub assume_yes: bool,
+    pub move_item: bool,
+    /// Directory to resurrect into instead of the origin inbox
+    pub destination: Option<PathBuf>,
+    pub preserve_times: bool,
+    pub on_conflict: ConflictPolicy,
+}
+
+/// A selected item and where it is going, settled before the Graveyard is locked.
+struct Resurrection<'a> {
+    entry: &'a IndexEntry,
+    /// The name the item had in its inbox
+    name: String,
+    dest_dir: PathBuf,
+    dest: PathBuf,
+    /// Whether an existing item at `dest` is overwritten
+    replace: bool,
+}
 
 pub struct GraveyardManager {
     config: Config,
@@ -15,209 +96,467 @@ impl GraveyardManager {
         Self { config }
     }
 
-    pub fn resurrect_files(&self, pattern: &str) -> Result<()> {
-        let matches = self.find_in_graveyard(pattern)?;
+    pub fn resurrect_files(&self, pattern: Option<&str>, options: &ResurrectOptions) -> Result<()> {
+        let query = pattern.map(SearchQuery::substring).unwrap_or_default();
+        let matches: Vec<IndexEntry> = self
+            .find_in_graveyard(&query)?
+            .into_iter()
+            .map(|(entry, _)| entry)
+            .collect();
+        let description = pattern
+            .map(|pattern| format!("matching '{pattern}'"))
+            .unwrap_or_else(|| "at all".to_string());
 
         if matches.is_empty() {
-            println!("💀 No files found matching '{pattern}' in the Graveyard");
+            if options.assume_yes || options.selection != Selection::Prompt {
+                anyhow::bail!("No files found {description} in the Graveyard");
+            }
+            println!("💀 No files found {description} in the Graveyard");
             return Ok(());
         }
 
         println!("Found {} matches:", matches.len());
-        for (i, path) in matches.iter().enumerate() {
-            println!("  {}. {}", i + 1, path.display());
+        for (i, entry) in matches.iter().enumerate() {
+            println!(
+                "  {}. {}",
+                i + 1,
+                self.config.graveyard.join(&entry.path).display()
+            );
+            println!("     {}", entry.summary());
+            if let Some(note) = &entry.epitaph {
+                println!("     💭 \"{note}\"");
+            }
         }
 
-        if matches.len() == 1 {
-            // Auto-resurrect single match
-            let source = &matches[0];
-            let filename = source
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("unknown");
-            let dest = self.config.inbox.join(filename);
-
-            copy_file_or_dir(source, &dest)?;
-            println!("🧟‍♂️ Resurrected '{filename}' to Inbox!");
-        } else {
-            // Interactive selection for multiple matches
-            print!(
-                "\nWhich file to resurrect? (1-{}, or 'q' to quit): ",
+        let selected: Vec<&IndexEntry> = match &options.selection {
+            Selection::All => matches.iter().collect(),
+            // Matches are listed newest first
+            Selection::Latest => matches.iter().take(1).collect(),
+            Selection::Index(index) => match index.checked_sub(1).and_then(|i| matches.get(i)) {
+                Some(entry) => vec![entry],
+                None => anyhow::bail!(
+                    "There is no match number {index}; pick one between 1 and {}",
+                    matches.len()
+                ),
+            },
+            Selection::Id(id) => {
+                let found: Vec<&IndexEntry> = matches
+                    .iter()
+                    .filter(|entry| entry.id().starts_with(id.as_str()))
+                    .collect();
+                match found.len() {
+                    0 => anyhow::bail!("No archived item with id '{id}' {description}"),
+                    1 => found,
+                    _ => anyhow::bail!("Id '{id}' is ambiguous; use more characters"),
+                }
+            }
+            Selection::Prompt if matches.len() == 1 => matches.iter().collect(),
+            Selection::Prompt if options.assume_yes => anyhow::bail!(
+                "{} files found {description}; pick one with --index, --latest, --id or --all",
                 matches.len()
-            );
-            io::stdout().flush()?;
+            ),
+            Selection::Prompt => {
+                // Interactive selection for multiple matches
+                print!(
+                    "\nWhich file to resurrect? (1-{}, or 'q' to quit): ",
+                    matches.len()
+                );
+                io::stdout().flush()?;
 
-            let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
-            let input = input.trim();
+                let mut input = String::new();
+                io::stdin().read_line(&mut input)?;
+                let input = input.trim();
 
-            if input == "q" {
-                println!("Resurrection cancelled.");
-                return Ok(());
-            }
-
-            if let Ok(choice) = input.parse::<usize>() {
-                if choice > 0 && choice <= matches.len() {
-                    let source = &matches[choice - 1];
-                    let filename = source
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("unknown");
-                    let dest = self.config.inbox.join(filename);
+                if input == "q" {
+                    println!("Resurrection cancelled.");
+                    return Ok(());
+                }
 
-                    copy_file_or_dir(source, &dest)?;
-                    println!("🧟‍♂️ Resurrected '{filename}' to Inbox!");
-                } else {
-                    println!("Invalid choice.");
+                match input.parse::<usize>() {
+                    Ok(choice) if choice > 0 && choice <= matches.len() => {
+                        vec![&matches[choice - 1]]
+                    }
+                    Ok(_) => {
+                        println!("Invalid choice.");
+                        return Ok(());
+                    }
+                    Err(_) => {
+                        println!("Invalid input.");
+                        return Ok(());
+                    }
                 }
-            } else {
-                println!("Invalid input.");
+            }
+        };
+
+        // Settle every destination first: conflicts may need an answer, and
+        // the Graveyard isn't kept locked while waiting for one
+        let mut planned = Vec::new();
+        let mut claimed = HashSet::new();
+        for entry in selected {
+            if let Some(resurrection) = self.plan(entry, options, &claimed)? {
+                claimed.insert(resurrection.dest.clone());
+                planned.push(resurrection);
             }
         }
 
+        let _lock = Lock::graveyard(&self.config)?;
+        for resurrection in planned {
+            self.resurrect(resurrection, options)?;
+        }
+
         Ok(())
     }
 
-    pub fn search_files(&self, pattern: &str) -> Result<()> {
-        let matches = self.find_in_graveyard(pattern)?;
+    /// Where `entry` is resurrected to, or `None` when it is skipped.
+    fn plan<'a>(
+        &self,
+        entry: &'a IndexEntry,
+        options: &ResurrectOptions,
+        claimed: &HashSet<PathBuf>,
+    ) -> Result<Option<Resurrection<'a>>> {
+        let source = self.config.graveyard.join(&entry.path);
+        let epitaph = Epitaph::load_for(&source)?;
+        let name = Self::original_name(entry, epitaph.as_ref());
+        let dest_dir = options
+            .destination
+            .clone()
+            .unwrap_or_else(|| self.origin_inbox(entry));
 
-        if matches.is_empty() {
-            println!("💀 No files found matching '{pattern}' in the Graveyard");
+        let Some((dest, replace)) = Self::resolve_conflict(dest_dir.join(&name), options, claimed)?
+        else {
+            println!(
+                "⏭️  Skipped '{name}': already exists in {}",
+                dest_dir.display()
+            );
+            return Ok(None);
+        };
+
+        Ok(Some(Resurrection {
+            entry,
+            name,
+            dest_dir,
+            dest,
+            replace,
+        }))
+    }
+
+    fn resurrect(&self, resurrection: Resurrection, options: &ResurrectOptions) -> Result<()> {
+        let Resurrection {
+            entry,
+            name,
+            dest_dir,
+            dest,
+            replace,
+        } = resurrection;
+        let source = self.config.graveyard.join(&entry.path);
+        // Another process may have got there while the destination was being settled
+        if source.symlink_metadata().is_err() {
+            anyhow::bail!("'{}' is no longer in the Graveyard", source.display());
+        }
+        if !replace && dest.symlink_metadata().is_ok() {
+            anyhow::bail!("'{}' was created in the meantime", dest.display());
+        }
+        let epitaph = Epitaph::load_for(&source)?;
+
+        let verb = match (entry.compression, options.move_item) {
+            (Some(_), _) => "unpack",
+            (None, true) => "move",
+            (None, false) => "copy",
+        };
+        if self.config.dry_run {
+            if replace {
+                println!("🔍 Would overwrite {}", dest.display());
+            }
+            println!(
+                "🔍 Would {verb} '{}' -> {}",
+                source.display(),
+                dest.display()
+            );
+            if options.move_item {
+                println!(
+                    "🔍 Would remove its copies, symlink views and epitaphs from the Graveyard"
+                );
+            }
             return Ok(());
         }
 
-        println!("Found {} matches in the Graveyard:", matches.len());
-        for path in matches {
-            // Show relative path from graveyard root
-            if let Ok(relative) = path.strip_prefix(&self.config.graveyard) {
-                println!("  📄 {}", relative.display());
-            } else {
-                println!("  📄 {}", path.display());
-            }
-
-            // Check for epitaph and show if it contains the search pattern
-            let epitaph_path = if path.is_file() {
-                path.with_extension(format!(
-                    "{}.epitaph",
-                    path.extension().and_then(|s| s.to_str()).unwrap_or("txt")
-                ))
-            } else {
-                path.join(".epitaph")
-            };
-
-            if epitaph_path.exists() {
-                if let Ok(epitaph_content) = std::fs::read_to_string(&epitaph_path) {
-                    // Extract just the note part (after the header)
-                    if let Some(note_start) = epitaph_content.find("\n\n") {
-                        let note = epitaph_content[note_start + 2..].trim();
-                        if !note.is_empty() {
-                            // Check if this match was found because of epitaph content
-                            let filename_matches = path
-                                .file_name()
-                                .and_then(|f| f.to_str())
-                                .map(|f| f.contains(pattern))
-                                .unwrap_or(false);
-
-                            if !filename_matches
-                                && epitaph_content
-                                    .to_lowercase()
-                                    .contains(&pattern.to_lowercase())
-                            {
-                                println!("     💭 \"{note}\" 🔍");
-                            } else {
-                                println!("     💭 \"{note}\"");
-                            }
-                        }
-                    }
+        // Collect symlink views while their targets still resolve
+        let traces = if options.move_item {
+            self.graveyard_traces(entry)?
+        } else {
+            Vec::new()
+        };
+
+        fs::create_dir_all(&dest_dir).context("Failed to create destination directory")?;
+        if replace {
+            delete_item(&dest)?;
+        }
+
+        match (entry.compression, options.move_item) {
+            // Bundles are unpacked, which keeps their recorded times
+            (Some(compression), _) => bundle::unpack(&source, &dest, compression)?,
+            (None, true) => {
+                ContentStore::materialize(&self.config, &source)?;
+                move_item(&source, &dest).context("Failed to move item out of the Graveyard")?;
+            }
+            (None, false) => {
+                copy_file_or_dir(&source, &dest)?;
+                if options.preserve_times {
+                    copy_times(&source, &dest)?;
                 }
             }
         }
 
+        if options.move_item {
+            if entry.compression.is_some() {
+                fs::remove_file(&source).context("Failed to remove bundle")?;
+            }
+            for trace in &traces {
+                let Ok(metadata) = trace.symlink_metadata() else {
+                    continue;
+                };
+                if metadata.is_dir() {
+                    remove_dir_with_permissions(trace)?;
+                } else {
+                    fs::remove_file(trace)
+                        .context(format!("Failed to remove {}", trace.display()))?;
+                }
+                remove_empty_parents(trace, &self.config.graveyard);
+            }
+            remove_empty_parents(&source, &self.config.graveyard);
+
+            let mut removed = traces.clone();
+            removed.push(source.clone());
+            GraveyardIndex::remove(&self.config, &removed)?;
+            ContentStore::prune(&self.config)?;
+        }
+
+        Journal::record(
+            &self.config,
+            Operation::Resurrect {
+                source: source.clone(),
+                destination: dest.clone(),
+                moved: options.move_item,
+                compression: entry.compression,
+                epitaph: epitaph
+                    .filter(|_| options.move_item)
+                    .map(|epitaph| epitaph.serialize()),
+            },
+        );
+        println!("🧟‍♂️ Resurrected '{name}' to {}!", dest.display());
         Ok(())
     }
 
-    fn find_in_graveyard(&self, pattern: &str) -> Result<Vec<PathBuf>> {
-        let mut matches = Vec::new();
+    /// The name the item had in its inbox, before it was made unique in the Graveyard.
+    fn original_name(entry: &IndexEntry, epitaph: Option<&Epitaph>) -> String {
+        entry
+            .original_path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .or_else(|| {
+                epitaph
+                    .map(|epitaph| epitaph.name.clone())
+                    .filter(|name| !name.is_empty())
+            })
+            .unwrap_or_else(|| entry.name.clone())
+    }
 
-        if !self.config.graveyard.exists() {
-            return Ok(matches);
+    /// Apply the collision policy to `dest`, counting the paths `claimed` by
+    /// other items of the same resurrection as taken. Returns the path to write
+    /// to and whether an existing item must be replaced, or `None` to skip.
+    fn resolve_conflict(
+        dest: PathBuf,
+        options: &ResurrectOptions,
+        claimed: &HashSet<PathBuf>,
+    ) -> Result<Option<(PathBuf, bool)>> {
+        let taken = |path: &Path| path.symlink_metadata().is_ok() || claimed.contains(path);
+        if !taken(&dest) {
+            return Ok(Some((dest, false)));
         }
 
-        for entry in WalkDir::new(&self.config.graveyard)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file() || (e.file_type().is_dir() && e.depth() > 0))
-        {
-            let mut found_match = false;
+        let policy = match options.on_conflict {
+            ConflictPolicy::Ask if options.assume_yes => anyhow::bail!(
+                "'{}' already exists; pick --on-conflict rename, skip or overwrite",
+                dest.display()
+            ),
+            ConflictPolicy::Ask => {
+                print!(
+                    "⚠️  '{}' already exists. [o]verwrite, [r]ename or [s]kip? ",
+                    dest.display()
+                );
+                io::stdout().flush()?;
 
-            // Check filename match
-            if let Some(filename) = entry.file_name().to_str() {
-                if filename.contains(pattern) && !filename.ends_with(".epitaph") {
-                    found_match = true;
+                let mut input = String::new();
+                io::stdin().read_line(&mut input)?;
+                match input.trim().to_lowercase().as_str() {
+                    "o" | "overwrite" => ConflictPolicy::Overwrite,
+                    "r" | "rename" => ConflictPolicy::Rename,
+                    _ => ConflictPolicy::Skip,
                 }
             }
+            policy => policy,
+        };
 
-            // Check epitaph content match (only for non-epitaph files)
-            if !found_match
-                && !entry
+        Ok(match policy {
+            ConflictPolicy::Rename => {
+                let name = dest
                     .file_name()
-                    .to_str()
-                    .unwrap_or("")
-                    .ends_with(".epitaph")
+                    .context("Invalid path")?
+                    .to_string_lossy()
+                    .to_string();
+                let renamed = (1..)
+                    .map(|counter| dest.with_file_name(numbered_name(&name, counter)))
+                    .find(|path| !taken(path))
+                    .expect("some numbered name is free");
+                Some((renamed, false))
+            }
+            ConflictPolicy::Overwrite => Some((dest, true)),
+            ConflictPolicy::Skip | ConflictPolicy::Ask => None,
+        })
+    }
+
+    /// Everything in the Graveyard belonging to `entry` besides the stored item
+    /// itself: other copies, epitaphs and the symlink views pointing at them.
+    fn graveyard_traces(&self, entry: &IndexEntry) -> Result<Vec<PathBuf>> {
+        let identity = entry.identity();
+        let copies: Vec<PathBuf> = GraveyardIndex::load_or_build(&self.config)?
+            .entries
+            .iter()
+            .filter(|other| other.identity() == identity)
+            .map(|other| self.config.graveyard.join(&other.path))
+            .collect();
+
+        let mut traces: Vec<PathBuf> = copies.iter().map(|copy| Epitaph::path_for(copy)).collect();
+        // Match views by the path they name, not by the file behind it: with
+        // symlink dedup, unrelated items resolve to the same store blob
+        let targets: HashSet<PathBuf> = copies
+            .iter()
+            .chain(&traces)
+            .map(|path| canonical_location(path))
+            .collect();
+        let source = self.config.graveyard.join(&entry.path);
+        traces.extend(copies.into_iter().filter(|copy| *copy != source));
+
+        let store = ContentStore::root(&self.config);
+        for view in WalkDir::new(&self.config.graveyard)
+            .into_iter()
+            .filter_entry(|e| e.path() != store)
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path_is_symlink())
+        {
+            if symlink_target(view.path())
+                .is_some_and(|target| targets.contains(&canonical_location(&target)))
             {
-                let epitaph_path = if entry.file_type().is_file() {
-                    entry.path().with_extension(format!(
-                        "{}.epitaph",
-                        entry
-                            .path()
-                            .extension()
-                            .and_then(|s| s.to_str())
-                            .unwrap_or("txt")
-                    ))
-                } else {
-                    entry.path().join(".epitaph")
-                };
+                traces.push(view.into_path());
+            }
+        }
 
-                if epitaph_path.exists() {
-                    if let Ok(epitaph_content) = std::fs::read_to_string(&epitaph_path) {
-                        if epitaph_content
-                            .to_lowercase()
-                            .contains(&pattern.to_lowercase())
-                        {
-                            found_match = true;
-                        }
-                    }
+        Ok(traces)
+    }
+
+    /// The inbox an archived item came from, as recorded in the index.
+    /// Falls back to the default inbox for unknown or older archives.
+    fn origin_inbox(&self, entry: &IndexEntry) -> PathBuf {
+        entry
+            .inbox
+            .as_deref()
+            .and_then(|name| self.config.find_inbox(name))
+            .map(|inbox| inbox.path)
+            .unwrap_or_else(|| self.config.inbox.clone())
+    }
+
+    pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        let hits = self
+            .find_in_graveyard(query)?
+            .into_iter()
+            .map(|(entry, matched_epitaph)| SearchHit {
+                id: entry.id(),
+                members: query.matching_members(&entry),
+                path: self.config.graveyard.join(&entry.path),
+                relative_path: entry.path,
+                host: entry.host,
+                archived: entry.archived,
+                modified: entry.modified,
+                size: entry.size,
+                is_directory: entry.is_directory,
+                note: entry.epitaph,
+                matched_epitaph,
+            })
+            .collect();
+
+        Ok(hits)
+    }
+
+    pub fn search_files(&self, query: &SearchQuery) -> Result<()> {
+        let hits = self.search(query)?;
+
+        if !self.config.output.is_text() {
+            return print_records(self.config.output, &hits);
+        }
+
+        if hits.is_empty() {
+            match &query.pattern {
+                Some(pattern) => {
+                    println!("💀 No files found matching '{pattern}' in the Graveyard")
                 }
+                None => println!("💀 No files in the Graveyard match these filters"),
+            }
+            return Ok(());
+        }
+
+        println!("Found {} matches in the Graveyard:", hits.len());
+        for hit in hits {
+            println!("  📄 {}", hit.relative_path.display());
+            for member in &hit.members {
+                println!("     📦 {member}");
             }
 
-            if found_match {
-                matches.push(entry.path().to_path_buf());
+            if let Some(note) = &hit.note {
+                if hit.matched_epitaph {
+                    println!("     💭 \"{note}\" 🔍");
+                } else {
+                    println!("     💭 \"{note}\"");
+                }
             }
         }
 
-        // Deduplicate - prefer files in the "created" subdir (originals over symlinks)
-        matches.sort_by(|a, b| {
-            let a_is_created = a.to_string_lossy().contains("/created/");
-            let b_is_created = b.to_string_lossy().contains("/created/");
-            match (a_is_created, b_is_created) {
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => a.cmp(b),
-            }
+        Ok(())
+    }
+
+    /// Index entries matching `query`, each with whether only its epitaph matched.
+    fn find_in_graveyard(&self, query: &SearchQuery) -> Result<Vec<(IndexEntry, bool)>> {
+        // Only the note is searched; the epitaph header is the same for every item
+        let mut matches: Vec<(IndexEntry, bool)> = GraveyardIndex::load_or_build(&self.config)?
+            .entries
+            .into_iter()
+            .filter_map(|entry| {
+                query
+                    .matches(&entry)
+                    .map(|matched_epitaph| (entry, matched_epitaph))
+            })
+            .collect();
+
+        // Copies of one archived item share its identity; keep the one under the
+        // primary original subdir so each distinct archived version shows up once
+        let primary = self.config.path_format.primary_subdir().map(Path::new);
+        matches.sort_by_key(|(entry, _)| {
+            let is_primary = primary.is_some_and(|subdir| entry.path.starts_with(subdir));
+            (!is_primary, entry.path.clone())
         });
 
-        // Remove duplicates (same filename)
         let mut deduped = Vec::new();
-        let mut seen_names = std::collections::HashSet::new();
+        let mut seen = std::collections::HashSet::new();
 
-        for path in matches {
-            if let Some(filename) = path.file_name() {
-                if seen_names.insert(filename.to_os_string()) {
-                    deduped.push(path);
-                }
+        for (entry, matched_epitaph) in matches {
+            if seen.insert(entry.identity()) {
+                deduped.push((entry, matched_epitaph));
             }
         }
 
+        // Newest archives first
+        deduped.sort_by_key(|(entry, _)| std::cmp::Reverse(entry.archived));
+
         Ok(deduped)
     }
 }
@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use globset::{Glob, GlobMatcher};
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+
+/// Name of the per-inbox file listing entries Relfa should leave alone.
+pub const IGNORE_FILE_NAME: &str = ".relfaignore";
+
+struct IgnorePattern {
+    matcher: GlobMatcher,
+    negated: bool,
+    directory_only: bool,
+}
+
+/// Gitignore-style exclusion rules for the top-level entries of an inbox.
+///
+/// Patterns come from the `ignore` list in the config followed by the
+/// inbox's `.relfaignore`, and the last matching pattern wins. A leading `!`
+/// re-includes entries, and a trailing `/` restricts a pattern to directories.
+pub struct IgnoreRules {
+    patterns: Vec<IgnorePattern>,
+}
+
+impl IgnoreRules {
+    pub fn for_inbox(config: &Config, inbox: &Path) -> Result<Self> {
+        let mut rules = Self {
+            patterns: Vec::new(),
+        };
+
+        for pattern in &config.ignore {
+            rules
+                .add_pattern(pattern)
+                .context(format!("Invalid ignore pattern in config: '{pattern}'"))?;
+        }
+
+        let ignore_file = inbox.join(IGNORE_FILE_NAME);
+        if ignore_file.is_file() {
+            let content = fs::read_to_string(&ignore_file).context(format!(
+                "Failed to read ignore file: {}",
+                ignore_file.display()
+            ))?;
+
+            for line in content.lines() {
+                rules.add_pattern(line).context(format!(
+                    "Invalid pattern '{}' in {}",
+                    line.trim(),
+                    ignore_file.display()
+                ))?;
+            }
+        }
+
+        Ok(rules)
+    }
+
+    fn add_pattern(&mut self, line: &str) -> Result<()> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (directory_only, pattern) = match pattern.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        // Only top-level entries are considered, so anchoring is implicit
+        let pattern = pattern.trim_start_matches('/');
+
+        self.patterns.push(IgnorePattern {
+            matcher: Glob::new(pattern)?.compile_matcher(),
+            negated,
+            directory_only,
+        });
+
+        Ok(())
+    }
+
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name() else {
+            return false;
+        };
+
+        if name == IGNORE_FILE_NAME {
+            return true;
+        }
+
+        let is_directory = path.is_dir();
+        let mut ignored = false;
+
+        for pattern in &self.patterns {
+            if pattern.directory_only && !is_directory {
+                continue;
+            }
+            if pattern.matcher.is_match(name) {
+                ignored = !pattern.negated;
+            }
+        }
+
+        ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn rules(patterns: &[&str]) -> IgnoreRules {
+        let mut rules = IgnoreRules {
+            patterns: Vec::new(),
+        };
+        for pattern in patterns {
+            rules.add_pattern(pattern).unwrap();
+        }
+        rules
+    }
+
+    /// Paths in this crate, so directory-only patterns see a real directory.
+    fn crate_path(relative: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join(relative)
+    }
+
+    #[test]
+    fn matches_globs_against_the_name() {
+        let rules = rules(&["*.iso", "/keep-me", "# a comment", ""]);
+
+        assert!(rules.is_ignored(Path::new("/inbox/ubuntu.iso")));
+        assert!(rules.is_ignored(Path::new("/inbox/keep-me")));
+        assert!(!rules.is_ignored(Path::new("/inbox/notes.txt")));
+        assert!(!rules.is_ignored(Path::new("/inbox/# a comment")));
+    }
+
+    #[test]
+    fn last_matching_pattern_wins() {
+        let rules = rules(&["*.pdf", "!invoice-*.pdf", "invoice-old.pdf"]);
+
+        assert!(rules.is_ignored(Path::new("report.pdf")));
+        assert!(!rules.is_ignored(Path::new("invoice-2024.pdf")));
+        assert!(rules.is_ignored(Path::new("invoice-old.pdf")));
+    }
+
+    #[test]
+    fn trailing_slash_only_matches_directories() {
+        let rules = rules(&["src/", "Cargo.toml/"]);
+
+        assert!(rules.is_ignored(&crate_path("src")));
+        assert!(!rules.is_ignored(&crate_path("Cargo.toml")));
+    }
+
+    #[test]
+    fn escaped_bang_is_literal() {
+        let rules = rules(&["\\!important"]);
+
+        assert!(rules.is_ignored(Path::new("!important")));
+        assert!(!rules.is_ignored(Path::new("important")));
+    }
+
+    #[test]
+    fn always_ignores_the_ignore_file() {
+        assert!(rules(&[]).is_ignored(Path::new("/inbox/.relfaignore")));
+    }
+}
@@ -0,0 +1,554 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::archiver::ArchivedItem;
+use crate::bundle;
+use crate::config::{Compression, Config, SubdirConfig};
+use crate::epitaph::{Epitaph, EPITAPH_EXTENSION};
+use crate::lock::Lock;
+use crate::migrate::{RETIRED_DIR_NAME, STAGING_DIR_NAME};
+use crate::store::{ContentStore, STORE_DIR_NAME};
+use crate::utils::{item_hash, item_size, symlink_target, write_atomic};
+
+pub const INDEX_FILE_NAME: &str = ".relfa-index.jsonl";
+
+/// One archived item as recorded in the Graveyard index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Stored file name, which may differ from the original to stay unique
+    pub name: String,
+    /// Location of the stored copy, relative to the Graveyard root
+    pub path: PathBuf,
+    pub inbox: Option<String>,
+    pub original_path: Option<PathBuf>,
+    pub host: Option<String>,
+    pub created: Option<DateTime<Utc>>,
+    pub modified: Option<DateTime<Utc>>,
+    pub archived: Option<DateTime<Utc>>,
+    pub is_directory: bool,
+    pub size: u64,
+    /// SHA-256 of the content (file paths and contents for folders)
+    pub hash: Option<String>,
+    /// Epitaph note, without its header
+    pub epitaph: Option<String>,
+    /// Format of the bundle the item is stored as, if compressed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Compression>,
+    /// Paths inside a compressed folder, so searches can look into it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<String>,
+}
+
+impl IndexEntry {
+    /// What makes two stored copies the same archived item: the moment and host
+    /// it was archived from, its name and its content. Items of unknown archive
+    /// date only match themselves.
+    pub fn identity(&self) -> String {
+        match self.archived {
+            Some(archived) => format!(
+                "{}|{}|{}|{}",
+                archived.timestamp(),
+                self.host.as_deref().unwrap_or(""),
+                self.name,
+                self.hash.as_deref().unwrap_or("")
+            ),
+            None => self.path.to_string_lossy().to_string(),
+        }
+    }
+
+    /// Short stable id of the archived item, shared by all its copies.
+    pub fn id(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let digest = hex::encode(Sha256::digest(self.identity().as_bytes()));
+        digest[..12].to_string()
+    }
+
+    /// One-line description of when and where the item was archived from.
+    pub fn summary(&self) -> String {
+        let archived = self
+            .archived
+            .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "unknown date".to_string());
+        let host = self.host.as_deref().unwrap_or("unknown host");
+        let kind = if self.is_directory { "📁" } else { "📄" };
+        let compressed = self
+            .compression
+            .map(|compression| format!(" · {}", compression.name()))
+            .unwrap_or_default();
+        format!(
+            "{kind} archived {archived} from {host}{compressed} · id {}",
+            self.id()
+        )
+    }
+}
+
+/// On-disk index of everything in the Graveyard, one JSON object per line.
+///
+/// The archiver appends to it, `relfa reindex` rewrites it from the tree.
+/// When the same path shows up more than once, the last line wins.
+pub struct GraveyardIndex {
+    pub entries: Vec<IndexEntry>,
+}
+
+impl GraveyardIndex {
+    /// Load the index, building it from the tree the first time it is needed.
+    pub fn load_or_build(config: &Config) -> Result<Self> {
+        let mut index = match Self::load(config)? {
+            Some(index) => index,
+            None => {
+                let _lock = Lock::graveyard(config)?;
+                // Another process may have built it while this one waited
+                match Self::load(config)? {
+                    Some(index) => index,
+                    None => {
+                        let index = Self::build(config)?;
+                        if !config.dry_run && config.graveyard.exists() {
+                            index.save(config)?;
+                        }
+                        return Ok(index);
+                    }
+                }
+            }
+        };
+
+        // Items removed behind our back (or by an undo) simply disappear
+        index
+            .entries
+            .retain(|entry| config.graveyard.join(&entry.path).exists());
+
+        Ok(index)
+    }
+
+    /// The index as written on disk, including entries whose item is gone,
+    /// or `None` if there is no index yet.
+    pub fn load(config: &Config) -> Result<Option<Self>> {
+        let path = Self::index_path(config);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path).context("Failed to read Graveyard index")?;
+        let mut by_path: HashMap<PathBuf, usize> = HashMap::new();
+        let mut entries: Vec<IndexEntry> = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: IndexEntry = serde_json::from_str(line).context(format!(
+                "Failed to parse Graveyard index line {}; run `relfa reindex`",
+                i + 1
+            ))?;
+            match by_path.get(&entry.path) {
+                Some(&existing) => entries[existing] = entry,
+                None => {
+                    by_path.insert(entry.path.clone(), entries.len());
+                    entries.push(entry);
+                }
+            }
+        }
+
+        Ok(Some(Self { entries }))
+    }
+
+    /// Walk the Graveyard and index every stored item (symlink views are skipped).
+    pub fn build(config: &Config) -> Result<Self> {
+        let entries = Self::item_paths(config)?
+            .iter()
+            .map(|path| Self::entry_from_tree(config, path))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { entries })
+    }
+
+    /// Every archived item stored in the Graveyard tree, in path order.
+    pub fn item_paths(config: &Config) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+
+        if !config.graveyard.exists() {
+            return Ok(paths);
+        }
+
+        let layout = Layout::survey(config)?;
+        let mut walker = Self::walk(config);
+        while let Some(entry) = walker.next() {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.depth() <= 1
+                || (entry.path_is_symlink() && !ContentStore::is_store_link(config, path))
+                || Epitaph::is_epitaph_path(path)
+            {
+                continue;
+            }
+
+            if !layout.is_item(config, path, entry.depth(), entry.file_type().is_dir()) {
+                continue;
+            }
+
+            paths.push(path.to_path_buf());
+            if entry.file_type().is_dir() {
+                // Everything inside belongs to this item
+                walker.skip_current_dir();
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// Walk the Graveyard, leaving out the index file, the store and a
+    /// migration in progress, which hold no items of their own.
+    fn walk(config: &Config) -> walkdir::FilterEntry<walkdir::IntoIter, fn(&DirEntry) -> bool> {
+        fn holds_items(entry: &DirEntry) -> bool {
+            entry.depth() != 1
+                || ![
+                    STORE_DIR_NAME,
+                    STAGING_DIR_NAME,
+                    RETIRED_DIR_NAME,
+                    INDEX_FILE_NAME,
+                ]
+                .contains(&entry.file_name().to_string_lossy().as_ref())
+        }
+
+        WalkDir::new(&config.graveyard)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(holds_items as fn(&DirEntry) -> bool)
+    }
+
+    /// Index the item stored at `path` from what is on disk and in its epitaph.
+    pub fn entry_from_tree(config: &Config, path: &Path) -> Result<IndexEntry> {
+        let relative = path
+            .strip_prefix(&config.graveyard)
+            .unwrap_or(path)
+            .to_path_buf();
+        let epitaph = Epitaph::load_for(path)?.unwrap_or_default();
+        let from_path = date_path_fields(config, &relative);
+
+        // Legacy items without a header: the date path of their subdir tells the day
+        let subdir = relative
+            .components()
+            .next()
+            .and_then(|subdir| subdir.as_os_str().to_str());
+        let date_of = |view: &SubdirConfig| {
+            from_path
+                .date
+                .filter(|_| subdir.is_some() && view.get_name() == subdir)
+        };
+
+        let created = epitaph
+            .created
+            .or_else(|| date_of(&config.path_format.created_subdir));
+        let modified = epitaph
+            .modified
+            .or_else(|| date_of(&config.path_format.modified_subdir))
+            .or_else(|| {
+                fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .map(DateTime::from)
+            });
+        let archived = epitaph
+            .archived
+            .or_else(|| date_of(&config.path_format.archived_subdir));
+
+        // Bundles describe what they hold in their tar headers
+        let contents = epitaph
+            .compressed
+            .map(|compression| bundle::contents(path, compression))
+            .transpose()?;
+
+        Ok(IndexEntry {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: relative,
+            inbox: epitaph.inbox.clone(),
+            original_path: None,
+            host: epitaph.hostname.clone().or(from_path.host),
+            created,
+            modified,
+            archived,
+            is_directory: match &contents {
+                Some(contents) => contents.is_directory,
+                None => path.is_dir(),
+            },
+            size: match &contents {
+                Some(contents) => contents.size,
+                None => item_size(path)?,
+            },
+            hash: Some(item_hash(path)?),
+            epitaph: epitaph.note().map(str::to_string),
+            compression: epitaph.compressed,
+            members: contents
+                .map(|contents| contents.members)
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Build the entry for a freshly archived item from the archiver's record.
+    pub fn entry_for(
+        config: &Config,
+        item: &ArchivedItem,
+        stored_path: &Path,
+        note: Option<&str>,
+        members: &[String],
+    ) -> Result<IndexEntry> {
+        Ok(IndexEntry {
+            name: stored_path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| item.name.clone()),
+            path: stored_path
+                .strip_prefix(&config.graveyard)
+                .unwrap_or(stored_path)
+                .to_path_buf(),
+            inbox: Some(item.inbox.clone()),
+            original_path: Some(item.original_path.clone()),
+            host: Some(config.get_hostname()),
+            created: Some(item.created),
+            modified: Some(item.modified),
+            archived: Some(item.archived),
+            is_directory: item.is_directory,
+            size: item.size,
+            hash: Some(item_hash(stored_path)?),
+            epitaph: note
+                .map(str::trim)
+                .filter(|note| !note.is_empty())
+                .map(str::to_string),
+            compression: item.compression,
+            members: members.to_vec(),
+        })
+    }
+
+    /// Append entries without rewriting the whole index.
+    pub fn append(config: &Config, entries: &[IndexEntry]) -> Result<()> {
+        if config.dry_run || entries.is_empty() {
+            return Ok(());
+        }
+
+        let _lock = Lock::graveyard(config)?;
+        let path = Self::index_path(config);
+        if !path.exists() {
+            // Index the existing tree first so older items are not lost
+            let mut index = Self::build(config)?;
+            for entry in entries {
+                index.entries.retain(|existing| existing.path != entry.path);
+                index.entries.push(entry.clone());
+            }
+            return index.save(config);
+        }
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .context("Failed to open Graveyard index")?;
+        for entry in entries {
+            writeln!(file, "{}", serde_json::to_string(entry)?)
+                .context("Failed to write Graveyard index")?;
+        }
+        Ok(())
+    }
+
+    /// Drop the entries stored at any of `paths` and rewrite the index.
+    pub fn remove(config: &Config, paths: &[PathBuf]) -> Result<()> {
+        if config.dry_run || !Self::index_path(config).exists() {
+            return Ok(());
+        }
+
+        let _lock = Lock::graveyard(config)?;
+        let mut index = Self::load_or_build(config)?;
+        index
+            .entries
+            .retain(|entry| !paths.contains(&config.graveyard.join(&entry.path)));
+        index.save(config)
+    }
+
+    pub fn save(&self, config: &Config) -> Result<()> {
+        let _lock = Lock::graveyard(config)?;
+        let mut content = String::new();
+        for entry in &self.entries {
+            content.push_str(&serde_json::to_string(entry)?);
+            content.push('\n');
+        }
+        fs::create_dir_all(&config.graveyard).context("Failed to create Graveyard directory")?;
+        write_atomic(&Self::index_path(config), &content).context("Failed to write Graveyard index")
+    }
+
+    fn index_path(config: &Config) -> PathBuf {
+        config.graveyard.join(INDEX_FILE_NAME)
+    }
+}
+
+/// Longest chain of symlink views followed to find the item at its end.
+const MAX_VIEW_HOPS: usize = 16;
+
+/// What the Graveyard tree says about where its items are, independently of
+/// the configured templates, which may have changed since they were archived.
+#[derive(Default)]
+struct Layout {
+    /// Items known from the index, their epitaph or a symlink view showing them
+    known: HashSet<PathBuf>,
+    /// Directories holding known items somewhere below, i.e. the date path
+    containers: HashSet<PathBuf>,
+    /// Depths of the known items in each subdir
+    known_depths: HashMap<String, BTreeSet<usize>>,
+    /// Depth of the shallowest file in each subdir: date paths hold no files,
+    /// so it sits right in a date directory
+    file_depths: HashMap<String, usize>,
+}
+
+impl Layout {
+    fn survey(config: &Config) -> Result<Self> {
+        let mut layout = Self::default();
+
+        if let Ok(Some(index)) = GraveyardIndex::load(config) {
+            layout.known.extend(
+                index
+                    .entries
+                    .iter()
+                    .map(|entry| config.graveyard.join(&entry.path)),
+            );
+        }
+
+        for entry in GraveyardIndex::walk(config) {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.depth() <= 1 {
+                continue;
+            }
+
+            if entry.path_is_symlink() && !ContentStore::is_store_link(config, path) {
+                if let Some(target) = Self::view_target(config, path) {
+                    layout.known.insert(target);
+                }
+            } else if Epitaph::is_epitaph_path(path) {
+                let name = entry.file_name().to_string_lossy();
+                let item = name
+                    .strip_suffix(&format!(".{EPITAPH_EXTENSION}"))
+                    .unwrap_or(&name);
+                layout.known.insert(path.with_file_name(item));
+            } else if !entry.file_type().is_dir() {
+                let depth = layout
+                    .file_depths
+                    .entry(Self::subdir_of(config, path))
+                    .or_insert(entry.depth());
+                *depth = (*depth).min(entry.depth());
+            }
+        }
+
+        layout.known.retain(|item| item.symlink_metadata().is_ok());
+        for item in &layout.known {
+            let Ok(relative) = item.strip_prefix(&config.graveyard) else {
+                continue;
+            };
+            layout
+                .known_depths
+                .entry(Self::subdir_of(config, item))
+                .or_default()
+                .insert(relative.components().count());
+            let mut parent = item.parent();
+            while let Some(dir) = parent.filter(|dir| *dir != config.graveyard) {
+                if !layout.containers.insert(dir.to_path_buf()) {
+                    break;
+                }
+                parent = dir.parent();
+            }
+        }
+
+        Ok(layout)
+    }
+
+    /// Whether the entry at `path`, `depth` levels below the Graveyard root,
+    /// is an archived item rather than part of a date path.
+    fn is_item(&self, config: &Config, path: &Path, depth: usize, is_dir: bool) -> bool {
+        if self.known.contains(path) {
+            return true;
+        }
+        if self.containers.contains(path) {
+            return false;
+        }
+
+        // Items nobody recorded sit at the same depth as the ones we know of,
+        // or failing that next to the shallowest file. A subdir with neither
+        // only holds views.
+        let subdir = Self::subdir_of(config, path);
+        let depths = match (
+            self.known_depths.get(&subdir),
+            self.file_depths.get(&subdir),
+        ) {
+            (Some(depths), _) => depths.clone(),
+            (None, Some(depth)) => BTreeSet::from([*depth]),
+            (None, None) => return false,
+        };
+        depths.contains(&depth) || !is_dir || depths.last().is_none_or(|deepest| depth > *deepest)
+    }
+
+    /// The stored item a symlink view shows, following views of views.
+    fn view_target(config: &Config, link: &Path) -> Option<PathBuf> {
+        let mut target = symlink_target(link)?;
+        for _ in 0..MAX_VIEW_HOPS {
+            if !target.is_symlink() || ContentStore::is_store_link(config, &target) {
+                break;
+            }
+            target = symlink_target(&target)?;
+        }
+        (target.starts_with(&config.graveyard) && !Epitaph::is_epitaph_path(&target))
+            .then_some(target)
+    }
+
+    fn subdir_of(config: &Config, path: &Path) -> String {
+        path.strip_prefix(&config.graveyard)
+            .ok()
+            .and_then(|relative| relative.components().next())
+            .map(|subdir| subdir.as_os_str().to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Default)]
+struct DatePathFields {
+    host: Option<String>,
+    date: Option<DateTime<Utc>>,
+}
+
+/// Recover the host and day from `<subdir>/<date path>/<name>` using the
+/// configured `date_format` template.
+fn date_path_fields(config: &Config, relative: &Path) -> DatePathFields {
+    let components: Vec<String> = relative
+        .components()
+        .skip(1)
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+    let mut fields = DatePathFields::default();
+    let (mut year, mut month, mut day) = (None, None, None);
+
+    for (template, value) in config
+        .path_format
+        .date_format
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .zip(&components)
+    {
+        match template {
+            "{hostname}" => fields.host = Some(value.clone()),
+            "{year}" => year = value.parse::<i32>().ok(),
+            "{month}" | "{month:02}" => month = value.parse::<u32>().ok(),
+            "{day}" | "{day:02}" => day = value.parse::<u32>().ok(),
+            _ => {}
+        }
+    }
+
+    fields.date = match (year, month, day) {
+        (Some(year), Some(month), Some(day)) => NaiveDate::from_ymd_opt(year, month, day)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|time| time.and_utc()),
+        _ => None,
+    };
+    fields
+}
@@ -0,0 +1,340 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::archiver::ArchivedItem;
+use crate::config::{Compression, Config};
+use crate::lock::Lock;
+use crate::state::state_dir;
+
+/// A change relfa made to the disk, with what is needed to revert it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "lowercase")]
+pub enum Operation {
+    Archive {
+        item: ArchivedItem,
+        /// Notification count the item had before it was archived
+        notification_count: u32,
+        /// When the item was first found stale, unknown for older entries
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        first_seen: Option<DateTime<Utc>>,
+        /// The item's notification times before it was archived
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        notified: Vec<DateTime<Utc>>,
+    },
+    Delete {
+        inbox: String,
+        name: String,
+        path: PathBuf,
+    },
+    Touch {
+        inbox: String,
+        name: String,
+        path: PathBuf,
+        /// Modification time before the touch
+        previous_modified: DateTime<Utc>,
+        notification_count: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        first_seen: Option<DateTime<Utc>>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        notified: Vec<DateTime<Utc>>,
+    },
+    Resurrect {
+        source: PathBuf,
+        destination: PathBuf,
+        /// Whether the item was moved out of the Graveyard rather than copied
+        #[serde(default)]
+        moved: bool,
+        /// Set when the item was unpacked from a bundle, to pack it again on undo
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        compression: Option<Compression>,
+        /// Epitaph removed along with a moved item, to put back on undo
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        epitaph: Option<String>,
+    },
+    Undo {
+        /// Id of the entry that was reverted
+        target: u64,
+    },
+}
+
+impl Operation {
+    pub fn describe(&self) -> String {
+        match self {
+            Operation::Archive { item, .. } => format!("archive of '{}'", item.name),
+            Operation::Delete { name, .. } => format!("delete of '{name}'"),
+            Operation::Touch { name, .. } => format!("touch of '{name}'"),
+            Operation::Resurrect { destination, .. } => {
+                format!("resurrection to {}", destination.display())
+            }
+            Operation::Undo { target } => format!("undo of entry {target}"),
+        }
+    }
+
+    /// Whether `undo` can revert this operation. Deletes are journaled, but
+    /// what they removed is gone for good.
+    pub fn is_undoable(&self) -> bool {
+        !matches!(self, Operation::Delete { .. } | Operation::Undo { .. })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub operation: Operation,
+}
+
+/// How much of the journal is read at a time when looking for its last entry.
+const JOURNAL_CHUNK: u64 = 8 * 1024;
+
+/// Append-only log of every operation, stored as one JSON object per line.
+pub struct Journal {
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    pub fn load() -> Result<Self> {
+        let path = Self::journal_path();
+        if !path.exists() {
+            return Ok(Self {
+                entries: Vec::new(),
+            });
+        }
+
+        let content = fs::read_to_string(&path).context("Failed to read journal file")?;
+        let entries = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(i, line)| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    // A damaged line only costs the operation it described
+                    eprintln!("⚠️  Skipping unreadable journal line {}: {e}", i + 1);
+                    None
+                }
+            })
+            .collect();
+
+        Ok(Self { entries })
+    }
+
+    /// Append `operation` to the journal. Nothing is recorded in dry-run mode.
+    ///
+    /// By the time an operation is recorded it has already happened, so a
+    /// journal that can't be written only costs the ability to undo it: the
+    /// failure is reported rather than returned.
+    pub fn record(config: &Config, operation: Operation) {
+        if config.dry_run {
+            return;
+        }
+
+        let description = operation.describe();
+        if let Err(e) = Self::append(config, operation) {
+            eprintln!("⚠️  Could not record the {description} in the journal, so it can't be undone: {e:#}");
+        }
+    }
+
+    fn append(config: &Config, operation: Operation) -> Result<()> {
+        let _lock = Lock::state(config)?;
+        let path = Self::journal_path();
+        let entry = JournalEntry {
+            id: Self::last_id(&path)? + 1,
+            timestamp: Utc::now(),
+            operation,
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create state directory")?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .context("Failed to open journal file")?;
+        // Don't glue the entry onto a line left unfinished by an interrupted write
+        let mut last_byte = [b'\n'];
+        if file.metadata().map(|m| m.len()).unwrap_or(0) > 0 {
+            file.seek(SeekFrom::End(-1))
+                .and_then(|_| file.read_exact(&mut last_byte))
+                .context("Failed to read journal file")?;
+        }
+        if last_byte[0] != b'\n' {
+            writeln!(file).context("Failed to write journal entry")?;
+        }
+        writeln!(file, "{}", serde_json::to_string(&entry)?)
+            .context("Failed to write journal entry")?;
+
+        Ok(())
+    }
+
+    /// Id of the newest readable entry, or 0 for an empty journal. Reads the
+    /// file backwards, so appending doesn't get slower as the journal grows.
+    fn last_id(path: &Path) -> Result<u64> {
+        #[derive(Deserialize)]
+        struct Id {
+            id: u64,
+        }
+
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e).context("Failed to open journal file"),
+        };
+        let mut start = file
+            .metadata()
+            .context("Failed to read journal file")?
+            .len();
+        let mut tail: Vec<u8> = Vec::new();
+
+        while start > 0 {
+            let chunk_len = start.min(JOURNAL_CHUNK);
+            start -= chunk_len;
+            let mut chunk = vec![0; chunk_len as usize];
+            file.seek(SeekFrom::Start(start))
+                .and_then(|_| file.read_exact(&mut chunk))
+                .context("Failed to read journal file")?;
+            chunk.append(&mut tail);
+            tail = chunk;
+
+            // The first line may be cut short unless the start of the file was reached
+            let mut lines: Vec<&[u8]> = tail.split(|byte| *byte == b'\n').collect();
+            let complete = if start == 0 { &lines[..] } else { &lines[1..] };
+            for line in complete.iter().rev() {
+                if let Ok(entry) = serde_json::from_slice::<Id>(line) {
+                    return Ok(entry.id);
+                }
+            }
+            // Keep only the partial first line for the next, earlier chunk
+            if start > 0 {
+                tail = lines.swap_remove(0).to_vec();
+            }
+        }
+
+        Ok(0)
+    }
+
+    /// Entries that can still be undone, newest first. Deletes are left out,
+    /// so they never stand in the way of older operations.
+    pub fn pending(&self) -> Vec<&JournalEntry> {
+        let undone: HashSet<u64> = self
+            .entries
+            .iter()
+            .filter_map(|entry| match entry.operation {
+                Operation::Undo { target } => Some(target),
+                _ => None,
+            })
+            .collect();
+
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.operation.is_undoable())
+            .filter(|entry| !undone.contains(&entry.id))
+            .collect()
+    }
+
+    fn journal_path() -> PathBuf {
+        state_dir().join("journal.jsonl")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, operation: Operation) -> JournalEntry {
+        JournalEntry {
+            id,
+            timestamp: Utc::now(),
+            operation,
+        }
+    }
+
+    fn resurrect(id: u64) -> JournalEntry {
+        entry(
+            id,
+            Operation::Resurrect {
+                source: PathBuf::from(format!("/graveyard/{id}")),
+                destination: PathBuf::from(format!("/inbox/{id}")),
+                moved: false,
+                compression: None,
+                epitaph: None,
+            },
+        )
+    }
+
+    fn delete(id: u64) -> JournalEntry {
+        entry(
+            id,
+            Operation::Delete {
+                inbox: "default".to_string(),
+                name: format!("{id}"),
+                path: PathBuf::from(format!("/inbox/{id}")),
+            },
+        )
+    }
+
+    fn ids(journal: &Journal) -> Vec<u64> {
+        journal.pending().iter().map(|entry| entry.id).collect()
+    }
+
+    #[test]
+    fn pending_lists_newest_first() {
+        let journal = Journal {
+            entries: vec![resurrect(1), resurrect(2), resurrect(3)],
+        };
+
+        assert_eq!(ids(&journal), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn pending_leaves_out_undone_entries_and_undos() {
+        let journal = Journal {
+            entries: vec![
+                resurrect(1),
+                resurrect(2),
+                entry(3, Operation::Undo { target: 2 }),
+                resurrect(4),
+            ],
+        };
+
+        assert_eq!(ids(&journal), vec![4, 1]);
+    }
+
+    #[test]
+    fn pending_skips_deletes() {
+        let journal = Journal {
+            entries: vec![resurrect(1), delete(2), delete(3)],
+        };
+
+        assert_eq!(ids(&journal), vec![1]);
+    }
+
+    #[test]
+    fn last_id_reads_past_damaged_lines_and_chunks() {
+        let path = std::env::temp_dir().join(format!("relfa-journal-{}.jsonl", std::process::id()));
+        let mut content = String::new();
+        // Spread entries over several chunks, then end on unreadable lines
+        for id in 1..=200 {
+            content.push_str(&serde_json::to_string(&resurrect(id)).unwrap());
+            content.push('\n');
+        }
+        content.push_str("not json\n{\"id\": ");
+        fs::write(&path, content).unwrap();
+
+        let last = Journal::last_id(&path);
+        let missing = Journal::last_id(&path.with_extension("missing"));
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(last.unwrap(), 200);
+        assert_eq!(missing.unwrap(), 0);
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::state::state_dir;
+
+/// How long to wait for another relfa process before giving up.
+const WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Lock files held by this process, so nested operations don't wait on themselves.
+static HELD: Mutex<Option<HashSet<PathBuf>>> = Mutex::new(None);
+
+/// An advisory lock keeping other relfa processes (a timer running
+/// `scan` or `archive` while `review` is open, say) away from the notification
+/// state or the Graveyard. Released when dropped.
+///
+/// When both are needed the Graveyard is locked first: the state lock is the
+/// innermost one, taken alone by the journal while the Graveyard may be held.
+/// Neither is held while waiting on the user.
+pub struct Lock {
+    // `None` when the lock was already held by this process, or in dry-run mode
+    held: Option<(PathBuf, File)>,
+}
+
+impl Lock {
+    /// Lock the notification state and the journal.
+    pub fn state(config: &Config) -> Result<Self> {
+        Self::acquire(
+            config,
+            &state_dir().join("state.lock"),
+            "the notification state",
+        )
+    }
+
+    /// Lock the Graveyard, its index and its content store.
+    pub fn graveyard(config: &Config) -> Result<Self> {
+        let path = state_dir().join("graveyard.lock");
+        debug_assert!(
+            Self::is_held(&path) || !Self::is_held(&state_dir().join("state.lock")),
+            "the Graveyard must be locked before the notification state"
+        );
+        Self::acquire(config, &path, "the Graveyard")
+    }
+
+    /// Take the lock at `path`, waiting up to `WAIT_TIMEOUT` for whoever has it.
+    fn acquire(config: &Config, path: &Path, what: &str) -> Result<Self> {
+        // Dry runs don't change anything, so they don't create lock files either
+        if config.dry_run || !Self::mark_held(path) {
+            return Ok(Self { held: None });
+        }
+
+        let file = Self::open(path).inspect_err(|_| Self::unmark_held(path))?;
+        let deadline = Instant::now() + WAIT_TIMEOUT;
+        let mut waiting = false;
+
+        loop {
+            match file.try_lock() {
+                Ok(()) => {
+                    return Ok(Self {
+                        held: Some((path.to_path_buf(), file)),
+                    })
+                }
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    if !waiting {
+                        eprintln!("⏳ Waiting for another relfa process to finish with {what}...");
+                        waiting = true;
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+                Err(TryLockError::WouldBlock) => {
+                    Self::unmark_held(path);
+                    anyhow::bail!(
+                        "Another relfa process is still using {what}; try again once it has finished (lock file: {})",
+                        path.display()
+                    );
+                }
+                Err(TryLockError::Error(e)) => {
+                    Self::unmark_held(path);
+                    return Err(e).context(format!("Failed to lock {what} ({})", path.display()));
+                }
+            }
+        }
+    }
+
+    fn open(path: &Path) -> Result<File> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create state directory")?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .context(format!("Failed to open lock file: {}", path.display()))
+    }
+
+    /// Record that this process holds `path`, unless it already did.
+    fn mark_held(path: &Path) -> bool {
+        let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+        held.get_or_insert_with(HashSet::new)
+            .insert(path.to_path_buf())
+    }
+
+    fn is_held(path: &Path) -> bool {
+        let held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+        held.as_ref().is_some_and(|held| held.contains(path))
+    }
+
+    fn unmark_held(path: &Path) {
+        let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(held) = held.as_mut() {
+            held.remove(path);
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        // Closing the file releases the lock
+        if let Some((path, _file)) = self.held.take() {
+            Self::unmark_held(&path);
+        }
+    }
+}
@@ -2,57 +2,187 @@ use anyhow::Result;
 use clap::Parser;
 
 mod archiver;
+mod bundle;
 mod cli;
 mod commands;
 mod config;
+mod epitaph;
+mod fsck;
 mod graveyard;
+mod ignore;
+mod index;
+mod journal;
+mod lock;
+mod migrate;
+mod output;
+mod query;
+mod repair;
+mod rules;
 mod scanner;
 mod state;
+mod store;
+mod template;
+mod tui;
 mod utils;
 
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, GraveyardCommand};
+use graveyard::{ResurrectOptions, Selection};
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let dry_run = cli.dry_run;
+    let output = cli.format;
+
+    if !output.is_text()
+        && !matches!(
+            cli.command,
+            Commands::Scan | Commands::Search { .. } | Commands::Archive { .. } | Commands::Fsck
+        )
+    {
+        anyhow::bail!("--format is only supported by the scan, search, archive and fsck commands");
+    }
+
+    // Banners are prose, so they are left out of machine-readable output
+    let say = |message: &str| {
+        if output.is_text() {
+            println!("{message}");
+        }
+    };
+
+    if dry_run {
+        say("🔍 Dry run: no changes will be made");
+    }
 
     match cli.command {
         Commands::Scan => {
-            println!("🕷️  Scanning Inbox for dusty files...");
-            commands::scan_inbox()?;
+            say("🕷️  Scanning Inbox for dusty files...");
+            commands::scan_inbox(dry_run, output)?;
         }
-        Commands::Review => {
-            println!("🔍 Starting interactive review...");
-            commands::interactive_review()?;
+        Commands::Review { plain } => {
+            say("🔍 Starting interactive review...");
+            commands::interactive_review(dry_run, plain)?;
         }
         Commands::Archive {
             item,
             all,
+            inbox,
             note,
             no_auto_archive,
         } => {
             if all {
-                println!("🪦 Archiving all eligible files...");
-                commands::archive_all_with_note(note.as_deref())?;
+                say("🪦 Archiving all eligible files...");
+                commands::archive_all_with_note(note.as_deref(), dry_run, output)?;
             } else if let Some(item) = item {
-                println!("🪦 Archiving {item}...");
-                commands::archive_item_with_note(&item, note.as_deref())?;
+                say(&format!("🪦 Archiving {item}..."));
+                commands::archive_item_with_note(
+                    &item,
+                    inbox.as_deref(),
+                    note.as_deref(),
+                    dry_run,
+                    output,
+                )?;
             } else if !no_auto_archive {
-                println!("🤖 Checking for files eligible for auto-archiving...");
-                commands::auto_archive_eligible_files(note.as_deref())?;
+                say("🤖 Checking for files eligible for auto-archiving...");
+                commands::auto_archive_eligible_files(note.as_deref(), dry_run, output)?;
+            } else {
+                say("Please specify either --all or an item to archive");
+            }
+        }
+        Commands::Snooze {
+            item,
+            duration,
+            until,
+            inbox,
+        } => {
+            say(&format!("💤 Snoozing {item}..."));
+            commands::snooze_item(
+                &item,
+                inbox.as_deref(),
+                duration.as_deref(),
+                until.as_deref(),
+                dry_run,
+            )?;
+        }
+        Commands::Pin { item, inbox } => {
+            say(&format!("📌 Pinning {item}..."));
+            commands::pin_item(&item, inbox.as_deref(), dry_run)?;
+        }
+        Commands::Unpin { item, inbox } => {
+            say(&format!("🔓 Unpinning {item}..."));
+            commands::unpin_item(&item, inbox.as_deref(), dry_run)?;
+        }
+        Commands::Pins => {
+            say("📌 Pinned and snoozed items:");
+            commands::list_pins()?;
+        }
+        Commands::Resurrect {
+            pattern,
+            index,
+            latest,
+            all,
+            id,
+            yes,
+            move_item,
+            to,
+            preserve_times,
+            on_conflict,
+        } => {
+            match &pattern {
+                Some(pattern) => say(&format!("🧟 Searching for '{pattern}' in the Graveyard...")),
+                None => say("🧟 Searching the Graveyard..."),
+            }
+            let selection = if let Some(index) = index {
+                Selection::Index(index)
+            } else if latest {
+                Selection::Latest
+            } else if all {
+                Selection::All
+            } else if let Some(id) = id {
+                Selection::Id(id)
             } else {
-                println!("Please specify either --all or an item to archive");
+                Selection::Prompt
+            };
+            let options = ResurrectOptions {
+                selection,
+                assume_yes: yes,
+                move_item,
+                destination: to,
+                preserve_times,
+                on_conflict,
+            };
+            commands::resurrect_files(pattern.as_deref(), &options, dry_run)?;
+        }
+        Commands::Search { query } => {
+            match &query.pattern {
+                Some(pattern) => say(&format!("🔍 Searching for '{pattern}' in the Graveyard...")),
+                None => say("🔍 Searching the Graveyard..."),
             }
+            commands::search_graveyard(&query, output)?;
+        }
+        Commands::Reindex => {
+            say("🗂️  Rebuilding the Graveyard index...");
+            commands::reindex(dry_run)?;
         }
-        Commands::Resurrect { pattern } => {
-            println!("🧟 Searching for '{pattern}' in the Graveyard...");
-            commands::resurrect_files(&pattern)?;
+        Commands::Fsck => {
+            say("🩺 Checking the Graveyard...");
+            commands::fsck(output)?;
         }
-        Commands::Search { pattern } => {
-            println!("🔍 Searching for '{pattern}' in the Graveyard...");
-            commands::search_graveyard(&pattern)?;
+        Commands::Graveyard { action } => match action {
+            GraveyardCommand::Repair => {
+                say("🔧 Repairing the Graveyard...");
+                commands::repair_graveyard(dry_run)?;
+            }
+            GraveyardCommand::Migrate => {
+                say("🚚 Migrating the Graveyard to the configured layout...");
+                commands::migrate_graveyard(dry_run)?;
+            }
+        },
+        Commands::Undo { last, id } => {
+            say("⏪ Undoing recorded operations...");
+            commands::undo(last, id, dry_run)?;
         }
         Commands::Config => {
-            println!("⚙️  Configuration:");
+            say("⚙️  Configuration:");
             commands::show_config()?;
         }
     }
@@ -0,0 +1,600 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::config::{Config, SubdirConfig, ViewItem};
+use crate::epitaph::Epitaph;
+use crate::index::{GraveyardIndex, IndexEntry, INDEX_FILE_NAME};
+use crate::lock::Lock;
+use crate::store::{ContentStore, STORE_DIR_NAME};
+use crate::utils::{
+    copy_times, create_relative_symlink, delete_item, numbered_name, symlink_target,
+};
+
+/// Where the new layout is built before it replaces the old one.
+pub const STAGING_DIR_NAME: &str = ".relfa-migrate";
+/// Where the old layout waits while the new one is swapped in.
+pub const RETIRED_DIR_NAME: &str = ".relfa-migrate-old";
+
+/// One archived item and where the configured layout puts it.
+#[derive(Debug)]
+pub struct MigratedItem {
+    /// Every stored copy of the item as it is laid out now
+    pub from: Vec<PathBuf>,
+    /// Stored copies under the configured layout, one per `original` subdir
+    pub originals: Vec<PathBuf>,
+    /// Symlink views, and epitaph links, with what each one points at
+    pub links: Vec<(PathBuf, PathBuf)>,
+    epitaph: String,
+    entry: IndexEntry,
+}
+
+impl MigratedItem {
+    /// Whether the item is already laid out as configured.
+    pub fn in_place(&self) -> bool {
+        let from: HashSet<&PathBuf> = self.from.iter().collect();
+        let originals: HashSet<&PathBuf> = self.originals.iter().collect();
+
+        from == originals
+            && self
+                .originals
+                .iter()
+                .all(|original| Epitaph::path_for(original).is_file())
+            && self
+                .links
+                .iter()
+                .all(|(link, target)| symlink_target(link).as_ref() == Some(target))
+    }
+}
+
+/// Every archived item in the Graveyard, re-laid out under the configured
+/// `path_format`.
+#[derive(Debug, Default)]
+pub struct Migration {
+    pub items: Vec<MigratedItem>,
+}
+
+impl Migration {
+    /// Work out where every item goes, from the timestamps in its epitaph or
+    /// index entry, or failing that from its path.
+    pub fn plan(config: &Config) -> Result<Self> {
+        let mut migration = Self::default();
+        if !config.graveyard.exists() {
+            return Ok(migration);
+        }
+
+        let index = GraveyardIndex::load(config)?;
+        let recorded: HashMap<PathBuf, IndexEntry> = index
+            .iter()
+            .flat_map(|index| &index.entries)
+            .map(|entry| (config.graveyard.join(&entry.path), entry.clone()))
+            .collect();
+
+        let mut paths: Vec<PathBuf> = GraveyardIndex::item_paths(config)?;
+        let mut missed: Vec<&PathBuf> = recorded
+            .keys()
+            .filter(|path| !paths.contains(path))
+            .filter(|path| {
+                path.symlink_metadata().is_ok()
+                    && (!path.is_symlink() || ContentStore::is_store_link(config, path))
+            })
+            .collect();
+        missed.sort();
+        paths.extend(missed.into_iter().cloned());
+
+        // Copies of the same item in several `original` subdirs become one
+        let mut copies: Vec<(IndexEntry, Epitaph, Vec<PathBuf>)> = Vec::new();
+        let mut by_identity: HashMap<String, usize> = HashMap::new();
+        for path in paths {
+            let mut entry = GraveyardIndex::entry_from_tree(config, &path)?;
+            let epitaph = Epitaph::load_for(&path)?;
+            let known = epitaph.clone().unwrap_or_default();
+            if let Some(old) = recorded.get(&path) {
+                entry.original_path = old.original_path.clone();
+                entry.inbox = entry.inbox.or_else(|| old.inbox.clone());
+                entry.host = known
+                    .hostname
+                    .clone()
+                    .or_else(|| old.host.clone())
+                    .or(entry.host);
+                entry.created = known.created.or(old.created);
+                entry.modified = known.modified.or(old.modified).or(entry.modified);
+                entry.archived = known.archived.or(old.archived).or(entry.archived);
+            }
+
+            match by_identity.get(&entry.identity()) {
+                Some(&existing) => copies[existing].2.push(path),
+                None => {
+                    by_identity.insert(entry.identity(), copies.len());
+                    copies.push((entry, epitaph.unwrap_or_default(), vec![path]));
+                }
+            }
+        }
+
+        let mut taken = HashSet::new();
+        for (mut entry, epitaph, from) in copies {
+            let item = plan_item(config, &mut entry, &epitaph, &mut taken)?;
+            migration.items.push(MigratedItem {
+                from,
+                epitaph: item.epitaph,
+                originals: item.originals,
+                links: item.links,
+                entry,
+            });
+        }
+
+        Ok(migration)
+    }
+
+    /// Whether any item is not laid out as configured.
+    pub fn is_needed(&self) -> bool {
+        self.items.iter().any(|item| !item.in_place())
+    }
+
+    /// Whatever the old layout holds besides the planned items, their
+    /// epitaphs and symlink views: files the plan could not tell are items,
+    /// strays, orphaned epitaphs. Replacing the old layout would lose them.
+    pub fn unplanned(&self, config: &Config) -> Result<Vec<PathBuf>> {
+        self.unplanned_in(config, &config.graveyard, &self.layout_subdirs(config)?)
+    }
+
+    /// `unplanned`, for the old layout's `subdirs` found under `base`.
+    fn unplanned_in(
+        &self,
+        config: &Config,
+        base: &Path,
+        subdirs: &[String],
+    ) -> Result<Vec<PathBuf>> {
+        let planned: HashSet<&Path> = self
+            .items
+            .iter()
+            .flat_map(|item| &item.from)
+            .filter_map(|path| path.strip_prefix(&config.graveyard).ok())
+            .collect();
+        let holding_planned: HashSet<&Path> = planned
+            .iter()
+            .flat_map(|path| path.ancestors().skip(1))
+            .collect();
+
+        let mut unplanned = Vec::new();
+        for subdir in subdirs {
+            let mut walker = WalkDir::new(base.join(subdir))
+                .sort_by_file_name()
+                .into_iter();
+            while let Some(entry) = walker.next() {
+                let entry = entry?;
+                let path = entry.path();
+                let relative = path.strip_prefix(base).unwrap_or(path);
+                let is_dir = entry.file_type().is_dir();
+
+                if planned.contains(relative) {
+                    if is_dir {
+                        walker.skip_current_dir();
+                    }
+                    continue;
+                }
+                let is_planned_epitaph = Epitaph::is_epitaph_path(relative)
+                    && planned.contains(relative.with_extension("").as_path());
+                // Links into a store are stored files; any other link is a view
+                let is_view = entry.path_is_symlink() && !ContentStore::is_blob(path);
+                if (is_dir && holding_planned.contains(relative)) || is_planned_epitaph || is_view {
+                    continue;
+                }
+                // Folders of views hold nothing to lose
+                if is_dir
+                    && WalkDir::new(path).into_iter().flatten().all(|e| {
+                        e.file_type().is_dir()
+                            || (e.path_is_symlink() && !ContentStore::is_blob(e.path()))
+                    })
+                {
+                    walker.skip_current_dir();
+                    continue;
+                }
+
+                unplanned.push(path.to_path_buf());
+                if is_dir {
+                    walker.skip_current_dir();
+                }
+            }
+        }
+        Ok(unplanned)
+    }
+
+    /// Build the new layout next to the old one, swap it in and rewrite the
+    /// index. Nothing in the old layout is touched until the new one is
+    /// complete, and a failed swap puts the old one back. The old layout is
+    /// only deleted if it holds nothing but what was moved into the new one.
+    pub fn apply(&self, config: &Config) -> Result<()> {
+        let _lock = Lock::graveyard(config)?;
+        let staging = config.graveyard.join(STAGING_DIR_NAME);
+        let retired = config.graveyard.join(RETIRED_DIR_NAME);
+        for dir in [&staging, &retired] {
+            if dir.symlink_metadata().is_ok() {
+                anyhow::bail!(
+                    "{} is left over from an interrupted migration; move it out of the way and try again",
+                    dir.display()
+                );
+            }
+        }
+
+        let subdirs = self.layout_subdirs(config)?;
+        let unplanned = self.unplanned_in(config, &config.graveyard, &subdirs)?;
+        if !unplanned.is_empty() {
+            anyhow::bail!(
+                "The Graveyard holds entries that are not archived items known to relfa, and migrating would delete them; move them out of the way (or run `relfa graveyard repair` for orphaned epitaphs) and try again:\n{}",
+                list_paths(&unplanned)
+            );
+        }
+
+        fs::create_dir_all(&staging).context("Failed to create migration directory")?;
+        if let Err(e) = self.stage(config, &staging) {
+            delete_item(&staging).ok();
+            return Err(
+                e.context("Failed to build the new layout; the Graveyard was left as it was")
+            );
+        }
+        self.swap(config, &subdirs, &staging, &retired)?;
+        fs::remove_dir(&staging).ok();
+
+        // Checked again on the real thing before anything is deleted
+        let unplanned = self.unplanned_in(config, &retired, &subdirs)?;
+        if unplanned.is_empty() {
+            delete_item(&retired)?;
+        }
+
+        let entries = self
+            .items
+            .iter()
+            .flat_map(|item| {
+                item.originals.iter().map(|original| IndexEntry {
+                    name: file_name(original),
+                    path: original
+                        .strip_prefix(&config.graveyard)
+                        .unwrap_or(original)
+                        .to_path_buf(),
+                    ..item.entry.clone()
+                })
+            })
+            .collect();
+        GraveyardIndex { entries }.save(config)?;
+
+        // Extra copies were made as plain files, so they get deduplicated again
+        if let Some(store) = ContentStore::new(config) {
+            for item in &self.items {
+                for copy in item.originals.iter().skip(1) {
+                    store.absorb(copy)?;
+                }
+            }
+        }
+
+        if !unplanned.is_empty() {
+            anyhow::bail!(
+                "The new layout is in place, but the old one was kept in {} because it holds entries that were not migrated; move out what you need and delete it:\n{}",
+                retired.display(),
+                list_paths(&unplanned)
+            );
+        }
+        Ok(())
+    }
+
+    fn stage(&self, config: &Config, staging: &Path) -> Result<()> {
+        let staged =
+            |path: &Path| staging.join(path.strip_prefix(&config.graveyard).unwrap_or(path));
+
+        for item in &self.items {
+            let source = &item.from[0];
+            for (i, original) in item.originals.iter().enumerate() {
+                let dest = staged(original);
+                fs::create_dir_all(dest.parent().unwrap()).context("Failed to create directory")?;
+                // The old layout goes away, so the first copy can share its content
+                link_tree(config, source, original, &dest, i == 0)?;
+                fs::write(Epitaph::path_for(&dest), &item.epitaph)
+                    .context("Failed to write epitaph file")?;
+            }
+            for (link, target) in &item.links {
+                let dest = staged(link);
+                fs::create_dir_all(dest.parent().unwrap()).context("Failed to create directory")?;
+                create_relative_symlink(&staged(target), &dest)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn swap(
+        &self,
+        config: &Config,
+        subdirs: &[String],
+        staging: &Path,
+        retired: &Path,
+    ) -> Result<()> {
+        let graveyard = &config.graveyard;
+        fs::create_dir(retired).context("Failed to create migration directory")?;
+
+        let mut moved_out = Vec::new();
+        let mut moved_in = Vec::new();
+        let result = (|| -> Result<()> {
+            for name in subdirs {
+                fs::rename(graveyard.join(name), retired.join(name))
+                    .context(format!("Failed to move {name} out of the way"))?;
+                moved_out.push(name.clone());
+            }
+            for entry in fs::read_dir(staging)? {
+                let name = entry?.file_name();
+                fs::rename(staging.join(&name), graveyard.join(&name)).context(format!(
+                    "Failed to move {} into place",
+                    name.to_string_lossy()
+                ))?;
+                moved_in.push(name);
+            }
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            // Put the old layout back as it was
+            for name in moved_in.iter().rev() {
+                fs::rename(graveyard.join(name), staging.join(name)).ok();
+            }
+            for name in moved_out.iter().rev() {
+                fs::rename(retired.join(name), graveyard.join(name)).ok();
+            }
+            fs::remove_dir(retired).ok();
+            delete_item(staging).ok();
+            return Err(
+                e.context("Failed to swap in the new layout; the Graveyard was left as it was")
+            );
+        }
+        Ok(())
+    }
+
+    /// Top-level folders of the old layout: those holding stored items, and
+    /// those holding nothing but symlink views.
+    fn layout_subdirs(&self, config: &Config) -> Result<Vec<String>> {
+        let with_items: HashSet<String> = self
+            .items
+            .iter()
+            .flat_map(|item| &item.from)
+            .filter_map(|path| {
+                let relative = path.strip_prefix(&config.graveyard).ok()?;
+                Some(
+                    relative
+                        .components()
+                        .next()?
+                        .as_os_str()
+                        .to_string_lossy()
+                        .to_string(),
+                )
+            })
+            .collect();
+
+        let mut subdirs = Vec::new();
+        for entry in fs::read_dir(&config.graveyard)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if [
+                INDEX_FILE_NAME,
+                STORE_DIR_NAME,
+                STAGING_DIR_NAME,
+                RETIRED_DIR_NAME,
+            ]
+            .contains(&name.as_str())
+                || !entry.file_type()?.is_dir()
+            {
+                continue;
+            }
+
+            let only_views = WalkDir::new(entry.path())
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .all(|e| e.file_type().is_dir() || e.path_is_symlink());
+            if with_items.contains(&name) || only_views {
+                subdirs.push(name);
+            }
+        }
+        subdirs.sort();
+        Ok(subdirs)
+    }
+}
+
+/// One indented path per line, for error messages.
+fn list_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| format!("   {}", path.display()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+struct Layout {
+    originals: Vec<PathBuf>,
+    links: Vec<(PathBuf, PathBuf)>,
+    epitaph: String,
+}
+
+/// Lay out one item, renaming it like the archiver would if its name is
+/// already taken by another item in any of its new locations.
+fn plan_item(
+    config: &Config,
+    entry: &mut IndexEntry,
+    epitaph: &Epitaph,
+    taken: &mut HashSet<PathBuf>,
+) -> Result<Layout> {
+    let modified = entry.modified.unwrap_or_else(Utc::now);
+    let item = ViewItem {
+        // Bundles are filed by the name of what they hold
+        name: match (epitaph.name.is_empty(), entry.compression) {
+            (false, _) => epitaph.name.clone(),
+            (true, Some(compression)) => entry
+                .name
+                .strip_suffix(&format!(".{}", compression.extension()))
+                .unwrap_or(&entry.name)
+                .to_string(),
+            (true, None) => entry.name.clone(),
+        },
+        is_directory: entry.is_directory,
+        inbox: entry.inbox.clone(),
+        hostname: entry.host.clone().unwrap_or_else(|| config.get_hostname()),
+        created: entry.created.unwrap_or(modified),
+        modified,
+        archived: entry.archived.unwrap_or(modified),
+    };
+    let views = config.path_format.views();
+
+    let mut counter = 0;
+    let layout = loop {
+        let name = match counter {
+            0 => entry.name.clone(),
+            _ => numbered_name(&entry.name, counter),
+        };
+        counter += 1;
+
+        // Where each view puts the item, for the symlink views pointing at it
+        let mut placed = HashMap::new();
+        let mut layout = Layout {
+            originals: Vec::new(),
+            links: Vec::new(),
+            epitaph: String::new(),
+        };
+        for view in &views {
+            if let SubdirConfig::Original { name: subdir } = view.subdir {
+                let path = config.view_location(view, &item, &name).unwrap();
+                placed.insert(subdir.clone(), path.clone());
+                layout.originals.push(path);
+            }
+        }
+        if layout.originals.is_empty() {
+            anyhow::bail!("path_format has no `original` subdir to store items in");
+        }
+        for view in &views {
+            if let SubdirConfig::Symlink {
+                name: subdir,
+                target,
+            } = view.subdir
+            {
+                let Some(target) = placed.get(target).cloned() else {
+                    continue;
+                };
+                let link = config.view_location(view, &item, &name).unwrap();
+                layout
+                    .links
+                    .push((Epitaph::path_for(&link), Epitaph::path_for(&target)));
+                layout.links.push((link.clone(), target));
+                placed.insert(subdir.clone(), link);
+            }
+        }
+
+        let free = layout
+            .originals
+            .iter()
+            .chain(layout.links.iter().map(|(link, _)| link))
+            .all(|path| !taken.contains(path) && !taken.contains(&Epitaph::path_for(path)));
+        if free {
+            entry.name = name;
+            break layout;
+        }
+    };
+
+    for path in layout
+        .originals
+        .iter()
+        .chain(layout.links.iter().map(|(link, _)| link))
+    {
+        taken.insert(path.clone());
+        taken.insert(Epitaph::path_for(path));
+    }
+
+    // Items from before epitaphs get one, so their timestamps survive the move
+    let epitaph = if *epitaph == Epitaph::default() {
+        Epitaph {
+            name: entry.name.clone(),
+            archived: entry.archived,
+            created: entry.created,
+            modified: entry.modified,
+            hostname: entry.host.clone(),
+            inbox: entry.inbox.clone(),
+            ..Epitaph::default()
+        }
+    } else {
+        epitaph.clone()
+    };
+    Ok(Layout {
+        epitaph: epitaph.serialize(),
+        ..layout
+    })
+}
+
+/// Recreate the item at `source` at `dest`, as hardlinks when `share` is set
+/// and as copies otherwise. Links into the content store are rebuilt for
+/// `location`, where `dest` ends up once the new layout is swapped in.
+fn link_tree(
+    config: &Config,
+    source: &Path,
+    location: &Path,
+    dest: &Path,
+    share: bool,
+) -> Result<()> {
+    for entry in WalkDir::new(source) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(source).unwrap_or(entry.path());
+        let (target, final_path) = if relative.as_os_str().is_empty() {
+            (dest.to_path_buf(), location.to_path_buf())
+        } else {
+            (dest.join(relative), location.join(relative))
+        };
+
+        if entry.path_is_symlink() {
+            let link = if ContentStore::is_store_link(config, entry.path()) {
+                store_link(config, entry.path(), &final_path)?
+            } else {
+                fs::read_link(entry.path())?
+            };
+            symlink(&link, &target)?;
+        } else if entry.file_type().is_dir() {
+            fs::create_dir_all(&target).context("Failed to create directory")?;
+        } else if !share || fs::hard_link(entry.path(), &target).is_err() {
+            fs::copy(entry.path(), &target).context("Failed to copy file")?;
+        }
+    }
+
+    if !source.is_symlink() {
+        copy_times(source, dest)?;
+    }
+    Ok(())
+}
+
+/// Relative target for a link to the same blob as `link`, placed at `location`.
+fn store_link(config: &Config, link: &Path, location: &Path) -> Result<PathBuf> {
+    let blob = symlink_target(link).context("Failed to read content store link")?;
+    let depth = location
+        .strip_prefix(&config.graveyard)
+        .unwrap_or(location)
+        .components()
+        .count();
+
+    let mut target = PathBuf::new();
+    for _ in 1..depth {
+        target.push("..");
+    }
+    target.push(STORE_DIR_NAME);
+    let components: Vec<_> = blob.components().collect();
+    for component in &components[components.len().saturating_sub(2)..] {
+        target.push(component.as_os_str());
+    }
+    Ok(target)
+}
+
+fn symlink(target: &Path, link: &Path) -> Result<()> {
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(target, link).context("Failed to create symlink")?;
+    #[cfg(windows)]
+    std::os::windows::fs::symlink_file(target, link).context("Failed to create symlink")?;
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
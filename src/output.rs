@@ -0,0 +1,40 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-friendly prose
+    #[default]
+    Text,
+    /// A single JSON array
+    Json,
+    /// One JSON object per line
+    Ndjson,
+}
+
+impl OutputFormat {
+    pub fn is_text(self) -> bool {
+        self == OutputFormat::Text
+    }
+}
+
+/// Print `records` to stdout in the requested machine-readable format.
+/// Does nothing in text mode, where callers print their own prose.
+pub fn print_records<T: Serialize>(format: OutputFormat, records: &[T]) -> Result<()> {
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => {
+            let json =
+                serde_json::to_string_pretty(records).context("Failed to serialize output")?;
+            println!("{json}");
+        }
+        OutputFormat::Ndjson => {
+            for record in records {
+                let json = serde_json::to_string(record).context("Failed to serialize output")?;
+                println!("{json}");
+            }
+        }
+    }
+    Ok(())
+}
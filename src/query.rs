@@ -0,0 +1,262 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Months, NaiveDate, Utc};
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+
+use crate::cli::{ItemType, SearchArgs};
+use crate::index::IndexEntry;
+use crate::utils::parse_size;
+
+enum Matcher {
+    /// Case-sensitive on names, case-insensitive on epitaphs
+    Substring(String),
+    Regex(Regex),
+    Glob(GlobMatcher),
+}
+
+impl Matcher {
+    fn matches_name(&self, name: &str) -> bool {
+        match self {
+            Matcher::Substring(pattern) => name.contains(pattern.as_str()),
+            Matcher::Regex(regex) => regex.is_match(name),
+            Matcher::Glob(glob) => glob.is_match(name),
+        }
+    }
+
+    fn matches_note(&self, note: &str) -> bool {
+        match self {
+            Matcher::Substring(pattern) => note.to_lowercase().contains(&pattern.to_lowercase()),
+            Matcher::Regex(regex) => regex.is_match(note),
+            Matcher::Glob(glob) => glob.is_match(note),
+        }
+    }
+}
+
+/// A half-open `[start, end)` time range.
+type Period = (DateTime<Utc>, DateTime<Utc>);
+
+/// Compiled search filters, evaluated against Graveyard index entries.
+#[derive(Default)]
+pub struct SearchQuery {
+    pub pattern: Option<String>,
+    matcher: Option<Matcher>,
+    epitaph_only: bool,
+    archived_after: Option<DateTime<Utc>>,
+    archived_before: Option<DateTime<Utc>>,
+    modified_between: Option<Period>,
+    host: Option<String>,
+    larger_than: Option<u64>,
+    item_type: Option<ItemType>,
+}
+
+impl SearchQuery {
+    /// The classic search: substring on names, case-insensitive on epitaphs.
+    pub fn substring(pattern: &str) -> Self {
+        Self {
+            pattern: Some(pattern.to_string()),
+            matcher: Some(Matcher::Substring(pattern.to_string())),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_args(args: &SearchArgs) -> Result<Self> {
+        let matcher = match &args.pattern {
+            None => None,
+            Some(pattern) if args.regex => Some(Matcher::Regex(
+                Regex::new(pattern).context(format!("Invalid regex '{pattern}'"))?,
+            )),
+            Some(pattern) if args.glob => Some(Matcher::Glob(
+                Glob::new(pattern)
+                    .context(format!("Invalid glob '{pattern}'"))?
+                    .compile_matcher(),
+            )),
+            Some(pattern) => Some(Matcher::Substring(pattern.clone())),
+        };
+
+        let modified_between = match args.modified_between.as_deref() {
+            Some([start, end]) => Some((parse_period(start)?.0, parse_period(end)?.1)),
+            _ => None,
+        };
+
+        Ok(Self {
+            pattern: args.pattern.clone(),
+            matcher,
+            epitaph_only: args.epitaph_only,
+            archived_after: args
+                .archived_after
+                .as_deref()
+                .map(|date| parse_period(date).map(|(start, _)| start))
+                .transpose()?,
+            archived_before: args
+                .archived_before
+                .as_deref()
+                .map(|date| parse_period(date).map(|(start, _)| start))
+                .transpose()?,
+            modified_between,
+            host: args.host.clone(),
+            larger_than: args
+                .larger_than
+                .as_deref()
+                .map(parse_size)
+                .transpose()
+                .context("Invalid --larger-than")?,
+            item_type: args.item_type,
+        })
+    }
+
+    /// Paths inside a compressed folder that the pattern matches.
+    pub fn matching_members(&self, entry: &IndexEntry) -> Vec<String> {
+        match &self.matcher {
+            Some(matcher) if !self.epitaph_only => entry
+                .members
+                .iter()
+                .filter(|member| matcher.matches_name(member))
+                .cloned()
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether `entry` passes every filter. Returns `Some(true)` when the
+    /// pattern only matched the epitaph note, `None` when it does not match.
+    pub fn matches(&self, entry: &IndexEntry) -> Option<bool> {
+        let matched_epitaph = match &self.matcher {
+            None => false,
+            Some(matcher) => {
+                let name_matches = !self.epitaph_only
+                    && (matcher.matches_name(&entry.name)
+                        || entry
+                            .members
+                            .iter()
+                            .any(|member| matcher.matches_name(member)));
+                let note_matches = entry
+                    .epitaph
+                    .as_deref()
+                    .is_some_and(|note| matcher.matches_note(note));
+                if !name_matches && !note_matches {
+                    return None;
+                }
+                !name_matches
+            }
+        };
+
+        if self.epitaph_only && entry.epitaph.is_none() {
+            return None;
+        }
+        // Items with an unknown archive date never pass a date filter
+        if let Some(after) = self.archived_after {
+            if entry.archived.is_none_or(|archived| archived < after) {
+                return None;
+            }
+        }
+        if let Some(before) = self.archived_before {
+            if entry.archived.is_none_or(|archived| archived >= before) {
+                return None;
+            }
+        }
+        if let Some((start, end)) = self.modified_between {
+            if !entry
+                .modified
+                .is_some_and(|modified| modified >= start && modified < end)
+            {
+                return None;
+            }
+        }
+        if let Some(host) = &self.host {
+            if entry.host.as_deref() != Some(host.as_str()) {
+                return None;
+            }
+        }
+        if let Some(limit) = self.larger_than {
+            if entry.size <= limit {
+                return None;
+            }
+        }
+        match self.item_type {
+            Some(ItemType::File) if entry.is_directory => return None,
+            Some(ItemType::Dir) if !entry.is_directory => return None,
+            _ => {}
+        }
+
+        Some(matched_epitaph)
+    }
+}
+
+/// Parse `YYYY`, `YYYY-MM` or `YYYY-MM-DD` into the period it spans, so
+/// `--archived-before 2025-04` means "before April 2025".
+fn parse_period(date: &str) -> Result<Period> {
+    let invalid = || format!("Invalid date '{date}', expected YYYY, YYYY-MM or YYYY-MM-DD");
+    let parts: Vec<&str> = date.split('-').collect();
+    let number = |part: &str| part.parse::<u32>().with_context(invalid);
+
+    let (start, end) = match parts.as_slice() {
+        [year] => {
+            let start = NaiveDate::from_ymd_opt(number(year)? as i32, 1, 1);
+            (
+                start,
+                start.and_then(|date| date.checked_add_months(Months::new(12))),
+            )
+        }
+        [year, month] => {
+            let start = NaiveDate::from_ymd_opt(number(year)? as i32, number(month)?, 1);
+            (
+                start,
+                start.and_then(|date| date.checked_add_months(Months::new(1))),
+            )
+        }
+        [year, month, day] => {
+            let start = NaiveDate::from_ymd_opt(number(year)? as i32, number(month)?, number(day)?);
+            (start, start.and_then(|date| date.succ_opt()))
+        }
+        _ => (None, None),
+    };
+
+    match (start, end) {
+        (Some(start), Some(end)) => Ok((
+            start.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+            end.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+        )),
+        _ => Err(anyhow::anyhow!(invalid())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn midnight(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_a_year() {
+        assert_eq!(
+            parse_period("2024").unwrap(),
+            (midnight(2024, 1, 1), midnight(2025, 1, 1))
+        );
+    }
+
+    #[test]
+    fn parses_a_month() {
+        assert_eq!(
+            parse_period("2024-12").unwrap(),
+            (midnight(2024, 12, 1), midnight(2025, 1, 1))
+        );
+    }
+
+    #[test]
+    fn parses_a_day() {
+        assert_eq!(
+            parse_period("2024-02-29").unwrap(),
+            (midnight(2024, 2, 29), midnight(2024, 3, 1))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        for date in ["", "24-1-1-1", "2023-02-29", "2024-13", "last-week"] {
+            assert!(parse_period(date).is_err(), "{date} should be rejected");
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::config::{Config, SubdirConfig};
+use crate::epitaph::Epitaph;
+use crate::index::GraveyardIndex;
+use crate::lock::Lock;
+use crate::utils::{create_relative_symlink, remove_empty_parents};
+
+/// What `relfa graveyard repair` did, or would do in dry-run mode.
+#[derive(Debug, Default)]
+pub struct RepairReport {
+    /// Dangling symlinks pointed back at their original
+    pub relinked: Vec<(PathBuf, PathBuf)>,
+    /// Dangling symlinks whose original is gone for good
+    pub removed_links: Vec<PathBuf>,
+    /// Epitaphs left behind by items that no longer exist
+    pub removed_epitaphs: Vec<PathBuf>,
+}
+
+/// Fix dangling symlink views and drop orphaned epitaphs.
+pub fn repair(config: &Config) -> Result<RepairReport> {
+    let _lock = Lock::graveyard(config)?;
+    let mut report = RepairReport::default();
+    if !config.graveyard.exists() {
+        return Ok(report);
+    }
+
+    // Orphaned epitaphs go first, so links to them count as dangling
+    for entry in WalkDir::new(&config.graveyard)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| !e.path_is_symlink() && Epitaph::is_epitaph_path(e.path()))
+    {
+        let epitaph = entry.path();
+        if epitaph.with_extension("").symlink_metadata().is_ok() {
+            continue;
+        }
+        report.removed_epitaphs.push(epitaph.to_path_buf());
+    }
+    let orphans: HashSet<PathBuf> = report
+        .removed_epitaphs
+        .iter()
+        .filter_map(|epitaph| epitaph.canonicalize().ok())
+        .collect();
+    if !config.dry_run {
+        for epitaph in &report.removed_epitaphs {
+            fs::remove_file(epitaph).context(format!("Failed to remove {}", epitaph.display()))?;
+            remove_empty_parents(epitaph, &config.graveyard);
+        }
+    }
+
+    let views = Views::build(config)?;
+    let symlink_subdirs =
+        config
+            .path_format
+            .views()
+            .into_iter()
+            .filter_map(|view| match view.subdir {
+                SubdirConfig::Symlink { name, .. } => Some(config.graveyard.join(name)),
+                _ => None,
+            });
+
+    for subdir in symlink_subdirs {
+        for entry in WalkDir::new(&subdir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path_is_symlink())
+        {
+            let link = entry.path();
+            let dangling = match link.canonicalize() {
+                Ok(target) => orphans.contains(&target),
+                Err(_) => true,
+            };
+            if !dangling {
+                continue;
+            }
+
+            match views.original_for(config, link) {
+                Some(original) => {
+                    if !config.dry_run {
+                        fs::remove_file(link)
+                            .context(format!("Failed to remove {}", link.display()))?;
+                        create_relative_symlink(&original, link)?;
+                    }
+                    report.relinked.push((link.to_path_buf(), original));
+                }
+                None => {
+                    if !config.dry_run {
+                        fs::remove_file(link)
+                            .context(format!("Failed to remove {}", link.display()))?;
+                        remove_empty_parents(link, &config.graveyard);
+                    }
+                    report.removed_links.push(link.to_path_buf());
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Where symlink views of the stored items should point.
+#[derive(Default)]
+struct Views {
+    /// Link location, derived from epitaph dates, to the item or view it shows
+    by_path: HashMap<PathBuf, PathBuf>,
+    /// Items or views shown by links without a date, by view subdir and name
+    by_name: HashMap<(String, String), Vec<PathBuf>>,
+}
+
+impl Views {
+    fn build(config: &Config) -> Result<Self> {
+        let mut views = Self::default();
+
+        for item in GraveyardIndex::item_paths(config)? {
+            let relative = item.strip_prefix(&config.graveyard).unwrap_or(&item);
+            let Some(subdir) = relative.components().next() else {
+                continue;
+            };
+            let subdir = subdir.as_os_str().to_string_lossy().to_string();
+            let name = item
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let epitaph = Epitaph::load_for(&item)?.unwrap_or_default();
+            let view_item = epitaph.view_item(config, &item)?;
+
+            // Where each view shows the item, for the views pointing at it
+            let mut placed = HashMap::from([(subdir, item.clone())]);
+            for view in config.path_format.views() {
+                let SubdirConfig::Symlink {
+                    name: view_name,
+                    target,
+                } = view.subdir
+                else {
+                    continue;
+                };
+                let Some(target) = placed.get(target).cloned() else {
+                    continue;
+                };
+
+                // Items with a known date have a known view, so only the
+                // others can claim a link by name
+                if epitaph.time(view.date).is_some() || !view.is_dated() {
+                    let link = config
+                        .graveyard
+                        .join(view_name)
+                        .join(config.format_view_path(&view, &view_item))
+                        .join(&name);
+                    views.by_path.insert(link.clone(), target);
+                    placed.insert(view_name.clone(), link);
+                } else {
+                    views
+                        .by_name
+                        .entry((view_name.clone(), name.clone()))
+                        .or_default()
+                        .push(target);
+                }
+            }
+        }
+
+        Ok(views)
+    }
+
+    /// The file the view at `link` should point at, if it can be told.
+    fn original_for(&self, config: &Config, link: &Path) -> Option<PathBuf> {
+        self.resolve(config, link, &mut HashSet::new())
+    }
+
+    /// `original_for`, giving up on views that lead back to one in `visited`,
+    /// as views configured to point at each other would.
+    fn resolve(
+        &self,
+        config: &Config,
+        link: &Path,
+        visited: &mut HashSet<PathBuf>,
+    ) -> Option<PathBuf> {
+        if !visited.insert(link.to_path_buf()) {
+            return None;
+        }
+
+        // Epitaph links follow the item they belong to
+        if Epitaph::is_epitaph_path(link) {
+            let item = self.resolve(config, &link.with_extension(""), visited)?;
+            return Some(Epitaph::path_for(&item)).filter(|epitaph| epitaph.is_file());
+        }
+
+        if let Some(target) = self.by_path.get(link) {
+            // A view showing another view that is gone points past it
+            if target.exists() {
+                return Some(target.clone());
+            }
+            return self.resolve(config, target, visited);
+        }
+
+        // A name that is unique within a view can only belong to one item
+        let relative = link.strip_prefix(&config.graveyard).ok()?;
+        let view_name = relative.components().next()?.as_os_str().to_string_lossy();
+        let name = link.file_name()?.to_string_lossy();
+        match self
+            .by_name
+            .get(&(view_name.to_string(), name.to_string()))?
+            .as_slice()
+        {
+            [item] => Some(item.clone()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use anyhow::{Context, Result};
+use globset::{Glob, GlobMatcher};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::config::{Compression, Inbox, RuleAction, RuleConfig, Settings};
+use crate::state::NotificationSpread;
+use crate::utils::{item_size, parse_size};
+
+/// The thresholds and action that apply to a single inbox entry, after
+/// evaluating the configured rules against it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemPolicy {
+    pub age_threshold_days: u32,
+    pub auto_archive_threshold_days: u32,
+    pub auto_archive_min_notified_days: u32,
+    pub auto_archive_min_notified_span_days: u32,
+    pub action: RuleAction,
+    /// Description of the rule that produced this policy, if any
+    pub rule: Option<String>,
+    /// Bundle format to archive the entry as, if it is compressed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compress: Option<Compression>,
+}
+
+impl ItemPolicy {
+    /// How many more days an item notified as in `spread` must be notified
+    /// on before it may be auto-archived. Every later day adds a notified day
+    /// and widens the span by at least one, so the larger shortfall decides.
+    pub fn days_needed(&self, spread: NotificationSpread) -> u32 {
+        let days = self
+            .auto_archive_min_notified_days
+            .saturating_sub(spread.days);
+        let span_days = match self.auto_archive_min_notified_span_days {
+            0 => 0,
+            // The span only starts on the first notified day
+            min if spread.days == 0 => min + 1,
+            min => min.saturating_sub(spread.span_days),
+        };
+        days.max(span_days)
+    }
+}
+
+struct CompiledRule {
+    config: RuleConfig,
+    glob: Option<GlobMatcher>,
+    mime: Option<GlobMatcher>,
+    extensions: Vec<String>,
+    larger_than: Option<u64>,
+    smaller_than: Option<u64>,
+}
+
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+    compress: Option<Compression>,
+    compress_larger_than: Option<u64>,
+}
+
+impl RuleSet {
+    pub fn new(config: &Settings) -> Result<Self> {
+        let mut rules = Vec::new();
+
+        for rule in &config.rules {
+            let description = rule.display();
+            let glob = rule
+                .glob
+                .as_deref()
+                .map(|glob| Glob::new(glob).map(|g| g.compile_matcher()))
+                .transpose()
+                .context(format!("Invalid glob in rule '{description}'"))?;
+            let mime = rule
+                .mime
+                .as_deref()
+                .map(|mime| Glob::new(mime).map(|g| g.compile_matcher()))
+                .transpose()
+                .context(format!("Invalid mime pattern in rule '{description}'"))?;
+            let larger_than = rule
+                .larger_than
+                .as_deref()
+                .map(parse_size)
+                .transpose()
+                .context(format!("Invalid larger_than in rule '{description}'"))?;
+            let smaller_than = rule
+                .smaller_than
+                .as_deref()
+                .map(parse_size)
+                .transpose()
+                .context(format!("Invalid smaller_than in rule '{description}'"))?;
+
+            rules.push(CompiledRule {
+                config: rule.clone(),
+                glob,
+                mime,
+                extensions: rule
+                    .extensions
+                    .iter()
+                    .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                    .collect(),
+                larger_than,
+                smaller_than,
+            });
+        }
+
+        let compress_larger_than = config
+            .compress_larger_than
+            .as_deref()
+            .map(parse_size)
+            .transpose()
+            .context("Invalid compress_larger_than")?;
+
+        Ok(Self {
+            rules,
+            compress: config.compress,
+            compress_larger_than,
+        })
+    }
+
+    /// Resolve the policy for `path`: the first matching rule's overrides on
+    /// top of the inbox thresholds, or the inbox thresholds alone.
+    pub fn policy_for(&self, inbox: &Inbox, path: &Path) -> Result<ItemPolicy> {
+        let mut policy = ItemPolicy {
+            age_threshold_days: inbox.age_threshold_days,
+            auto_archive_threshold_days: inbox.auto_archive_threshold_days,
+            auto_archive_min_notified_days: inbox.auto_archive_min_notified_days,
+            auto_archive_min_notified_span_days: inbox.auto_archive_min_notified_span_days,
+            action: RuleAction::Archive,
+            rule: None,
+            compress: None,
+        };
+
+        // Sizes can be expensive for folders, so compute at most once
+        let mut size = None;
+        let mut rule_compress = None;
+
+        for rule in &self.rules {
+            if !rule.matches(path, &mut size)? {
+                continue;
+            }
+
+            let config = &rule.config;
+            if let Some(days) = config.age_threshold_days {
+                policy.age_threshold_days = days;
+            }
+            if let Some(days) = config.auto_archive_threshold_days {
+                policy.auto_archive_threshold_days = days;
+            }
+            if let Some(days) = config.auto_archive_min_notified_days {
+                policy.auto_archive_min_notified_days = days;
+            }
+            if let Some(days) = config.auto_archive_min_notified_span_days {
+                policy.auto_archive_min_notified_span_days = days;
+            }
+            policy.action = config.action;
+            policy.rule = Some(config.display());
+            rule_compress = config.compress;
+            break;
+        }
+
+        // Without a rule saying otherwise, folders are always bundled and files
+        // only above the size limit
+        let compress = match rule_compress {
+            Some(compress) => Some(compress),
+            None if path.is_dir() => self.compress,
+            None => match self.compress_larger_than {
+                Some(limit) if self.compress.is_some() => {
+                    let size = match size {
+                        Some(size) => size,
+                        None => item_size(path)?,
+                    };
+                    self.compress.filter(|_| size > limit)
+                }
+                _ => None,
+            },
+        };
+        policy.compress = compress.filter(|compress| *compress != Compression::None);
+
+        Ok(policy)
+    }
+}
+
+impl CompiledRule {
+    fn matches(&self, path: &Path, size: &mut Option<u64>) -> Result<bool> {
+        let Some(name) = path.file_name() else {
+            return Ok(false);
+        };
+
+        if let Some(glob) = &self.glob {
+            if !glob.is_match(name) {
+                return Ok(false);
+            }
+        }
+
+        if !self.extensions.is_empty() {
+            let extension = path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase());
+            if path.is_dir() || !extension.is_some_and(|ext| self.extensions.contains(&ext)) {
+                return Ok(false);
+            }
+        }
+
+        if let Some(mime) = &self.mime {
+            if path.is_dir() {
+                return Ok(false);
+            }
+            let guessed = mime_guess::from_path(path).first_or_octet_stream();
+            if !mime.is_match(guessed.essence_str()) {
+                return Ok(false);
+            }
+        }
+
+        if self.larger_than.is_some() || self.smaller_than.is_some() {
+            let size = match size {
+                Some(size) => *size,
+                None => *size.insert(item_size(path)?),
+            };
+            if self.larger_than.is_some_and(|limit| size <= limit)
+                || self.smaller_than.is_some_and(|limit| size >= limit)
+            {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn rule_set(rules: &str, mut config: Settings) -> RuleSet {
+        #[derive(serde::Deserialize)]
+        struct Rules {
+            rules: Vec<RuleConfig>,
+        }
+        config.rules = toml::from_str::<Rules>(rules).unwrap().rules;
+        RuleSet::new(&config).unwrap()
+    }
+
+    fn inbox() -> Inbox {
+        Inbox {
+            name: "downloads".to_string(),
+            path: PathBuf::from("/inbox"),
+            age_threshold_days: 3,
+            auto_archive_threshold_days: 7,
+            auto_archive_min_notified_days: 2,
+            auto_archive_min_notified_span_days: 1,
+        }
+    }
+
+    /// Paths in this crate, for rules that look at sizes or directories.
+    fn crate_path(relative: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join(relative)
+    }
+
+    const RULES: &str = r#"
+        [[rules]]
+        name = "images"
+        glob = "*.iso"
+        age_threshold_days = 1
+        action = "delete"
+
+        [[rules]]
+        extensions = [".ISO", "pdf"]
+        age_threshold_days = 30
+        auto_archive_min_notified_days = 5
+
+        [[rules]]
+        glob = "keep-*"
+        action = "keep"
+    "#;
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = rule_set(RULES, Settings::default());
+
+        let policy = rules
+            .policy_for(&inbox(), Path::new("/inbox/ubuntu.iso"))
+            .unwrap();
+        assert_eq!(policy.age_threshold_days, 1);
+        assert_eq!(policy.action, RuleAction::Delete);
+        assert_eq!(policy.rule.as_deref(), Some("images (glob *.iso)"));
+        // Thresholds the rule leaves alone come from the inbox
+        assert_eq!(policy.auto_archive_threshold_days, 7);
+
+        let policy = rules
+            .policy_for(&inbox(), Path::new("/inbox/Report.PDF"))
+            .unwrap();
+        assert_eq!(policy.age_threshold_days, 30);
+        assert_eq!(policy.auto_archive_min_notified_days, 5);
+        assert_eq!(policy.action, RuleAction::Archive);
+
+        let policy = rules
+            .policy_for(&inbox(), Path::new("/inbox/keep-taxes.pdf"))
+            .unwrap();
+        assert_eq!(policy.age_threshold_days, 30);
+    }
+
+    #[test]
+    fn unmatched_items_get_the_inbox_thresholds() {
+        let rules = rule_set(RULES, Settings::default());
+
+        let policy = rules
+            .policy_for(&inbox(), Path::new("/inbox/notes.txt"))
+            .unwrap();
+        assert_eq!(policy.age_threshold_days, 3);
+        assert_eq!(policy.auto_archive_threshold_days, 7);
+        assert_eq!(policy.action, RuleAction::Archive);
+        assert_eq!(policy.rule, None);
+        assert_eq!(policy.compress, None);
+    }
+
+    #[test]
+    fn matches_on_size() {
+        let rules = rule_set(
+            r#"
+            [[rules]]
+            smaller_than = "1b"
+            action = "delete"
+
+            [[rules]]
+            larger_than = "10"
+            action = "keep"
+            "#,
+            Settings::default(),
+        );
+
+        let policy = rules
+            .policy_for(&inbox(), &crate_path("Cargo.toml"))
+            .unwrap();
+        assert_eq!(policy.action, RuleAction::Keep);
+    }
+
+    #[test]
+    fn bundles_folders_and_large_files() {
+        let mut config = Settings::default();
+        config.compress = Some(Compression::Zstd);
+        config.compress_larger_than = Some("1tb".to_string());
+        let rules = rule_set(
+            r#"
+            [[rules]]
+            glob = "*.toml"
+            compress = "gzip"
+
+            [[rules]]
+            glob = "src"
+            compress = "none"
+            "#,
+            config.clone(),
+        );
+
+        let policy = rules.policy_for(&inbox(), &crate_path("src")).unwrap();
+        assert_eq!(policy.compress, None);
+        let policy = rules
+            .policy_for(&inbox(), &crate_path("Cargo.toml"))
+            .unwrap();
+        assert_eq!(policy.compress, Some(Compression::Gzip));
+
+        let defaults = rule_set("rules = []", config);
+        let policy = defaults.policy_for(&inbox(), &crate_path("src")).unwrap();
+        assert_eq!(policy.compress, Some(Compression::Zstd));
+        let policy = defaults
+            .policy_for(&inbox(), &crate_path("Cargo.lock"))
+            .unwrap();
+        assert_eq!(policy.compress, None);
+    }
+
+    #[test]
+    fn days_needed_waits_for_the_larger_shortfall() {
+        let policy = rule_set("rules = []", Settings::default())
+            .policy_for(&inbox(), Path::new("/inbox/notes.txt"))
+            .unwrap();
+        let spread = |days, span_days| NotificationSpread { days, span_days };
+
+        // Two notified days spanning at least one day
+        assert_eq!(policy.days_needed(spread(0, 0)), 2);
+        assert_eq!(policy.days_needed(spread(1, 0)), 1);
+        assert_eq!(policy.days_needed(spread(2, 1)), 0);
+        assert_eq!(policy.days_needed(spread(3, 5)), 0);
+
+        let mut policy = policy;
+        policy.auto_archive_min_notified_span_days = 7;
+        assert_eq!(policy.days_needed(spread(0, 0)), 8);
+        assert_eq!(policy.days_needed(spread(2, 3)), 4);
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        let mut config = Settings::default();
+        config.rules = vec![toml::from_str("larger_than = \"10 parsecs\"").unwrap()];
+        assert!(RuleSet::new(&config).is_err());
+    }
+}
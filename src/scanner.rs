@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc;
@@ -7,20 +8,75 @@ use std::thread;
 use std::time::Duration as StdDuration;
 use walkdir::WalkDir;
 
-use crate::config::{Config, NotificationType};
-use crate::state::NotificationState;
+use crate::config::{Config, Inbox, NotificationType, RuleAction};
+use crate::ignore::IgnoreRules;
+use crate::lock::Lock;
+use crate::rules::{ItemPolicy, RuleSet};
+use crate::state::{FileIdentity, NotificationSpread, NotificationState};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct StaleItem {
     pub path: PathBuf,
     pub name: String,
+    pub inbox: String,
     pub last_modified: DateTime<Utc>,
     pub is_directory: bool,
     pub age_days: i64,
     pub notification_count: u32,
+    /// When a scan first found this very file stale
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_seen: Option<DateTime<Utc>>,
+    /// The days on which it was notified, which auto-archiving waits on
+    pub notified: NotificationSpread,
+    pub policy: ItemPolicy,
+    /// Which file this is, so renames keep and replacements lose its count
+    #[serde(skip)]
+    pub identity: FileIdentity,
+}
+
+/// Which list of a scan an item belongs to.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanCategory {
+    /// Past the age threshold
+    Stale,
+    /// Past the auto-archive threshold but not notified on enough days yet
+    Pending,
+    /// Will be handled by `relfa archive` without arguments
+    Eligible,
+}
+
+/// One line of machine-readable scan output.
+#[derive(Debug, Serialize)]
+pub struct ScanRecord {
+    pub category: ScanCategory,
+    #[serde(flatten)]
+    pub item: StaleItem,
+    /// Days of notifications still needed before a pending item becomes eligible
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub days_needed: Option<u32>,
+}
+
+/// Which of an item's thresholds its age is compared against.
+#[derive(Debug, Clone, Copy)]
+enum Threshold {
+    Stale,
+    AutoArchive,
 }
 
 impl StaleItem {
+    /// Fill in what `state` knows about notifications for this item.
+    fn load_notifications(&mut self, state: &NotificationState) {
+        self.notification_count = state.get_notification_count(&self.inbox, &self.identity);
+        self.first_seen = state.first_seen(&self.inbox, &self.identity);
+        self.notified = state.notification_spread(&self.inbox, &self.identity);
+    }
+
+    /// Days of notifications still needed before it may be auto-archived.
+    pub fn days_needed(&self) -> u32 {
+        self.policy.days_needed(self.notified)
+    }
+
     pub fn display(&self) -> String {
         let item_type = if self.is_directory { "folder" } else { "file" };
         format!(
@@ -51,107 +107,79 @@ impl Scanner {
     }
 
     fn scan_inbox_with_state(&self, track_notifications: bool) -> Result<Vec<StaleItem>> {
-        if !self.config.inbox.exists() {
-            return Ok(vec![]);
-        }
-
-        let mut state = if track_notifications {
-            Some(NotificationState::load().unwrap_or_default())
+        let _lock = if track_notifications {
+            Some(Lock::state(&self.config)?)
         } else {
             None
         };
-
+        let mut state = NotificationState::load().unwrap_or_default();
         let mut stale_items = Vec::new();
-        let mut currently_stale_files = std::collections::HashSet::new();
-        let cutoff_date = Utc::now() - Duration::days(self.config.age_threshold_days as i64);
+        let mut inbox_names = std::collections::HashSet::new();
+
+        for inbox in self.config.inboxes() {
+            inbox_names.insert(inbox.name.clone());
+            let mut currently_stale_files = Vec::new();
+
+            for mut item in self.find_items_past(&inbox, Threshold::Stale)? {
+                // Pinned and snoozed items are left alone, though a pin
+                // learns the item's current name
+                if let Some(hold) = state.hold(&inbox.name, &item.identity) {
+                    if track_notifications {
+                        state.set_hold(&inbox.name, &item.identity, Some(hold));
+                    }
+                    continue;
+                }
 
-        for entry in fs::read_dir(&self.config.inbox).context("Failed to read inbox directory")? {
-            let entry = entry.context("Failed to read directory entry")?;
-            let path = entry.path();
+                // Track that this file is currently stale
+                currently_stale_files.push(item.identity.clone());
 
-            if let Some(last_modified) = self.get_last_modified_time(&path)? {
-                if last_modified < cutoff_date {
-                    let age_days = (Utc::now() - last_modified).num_days();
-                    let name = path
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("unknown")
-                        .to_string();
-
-                    // Track that this file is currently stale
-                    currently_stale_files.insert(name.clone());
+                if track_notifications {
+                    state.increment_notification_count(&inbox.name, &item.identity);
+                }
+                item.load_notifications(&state);
 
-                    let notification_count = if let Some(ref mut state) = state {
-                        // Get current count and increment it
-                        let current_count = state.get_notification_count(&name);
-                        state.increment_notification_count(&name);
-                        current_count + 1 // Return what the count will be after this scan
-                    } else {
-                        // When not tracking, just load state to get current count
-                        NotificationState::load()
-                            .unwrap_or_default()
-                            .get_notification_count(&name)
-                    };
+                stale_items.push(item);
+            }
 
-                    stale_items.push(StaleItem {
-                        path: path.clone(),
-                        name,
-                        last_modified,
-                        is_directory: path.is_dir(),
-                        age_days,
-                        notification_count,
-                    });
-                }
+            // Clean up entries for files that are no longer stale
+            // This includes files that were deleted, modified, or are now younger than threshold
+            if track_notifications {
+                state.retain_only_files(
+                    &inbox.name,
+                    &currently_stale_files,
+                    &Self::inbox_identities(&inbox),
+                );
             }
         }
 
         // Save the updated state if we were tracking
-        if let Some(mut state) = state {
-            // Clean up entries for files that are no longer stale
-            // This includes files that were deleted, modified, or are now younger than threshold
-            state.retain_only_files(&currently_stale_files);
-            state.save()?;
+        if track_notifications {
+            state.retain_only_inboxes(&inbox_names);
+            state.save_unless_dry_run(
+                &self.config,
+                &format!("record a notification for {} items", stale_items.len()),
+            )?;
         }
 
         Ok(stale_items)
     }
 
     pub fn scan_auto_archive_eligible(&self) -> Result<Vec<StaleItem>> {
-        if !self.config.inbox.exists() {
-            return Ok(vec![]);
-        }
-
         let state = NotificationState::load().unwrap_or_default();
         let mut auto_archive_items = Vec::new();
-        let cutoff_date =
-            Utc::now() - Duration::days(self.config.auto_archive_threshold_days as i64);
 
-        for entry in fs::read_dir(&self.config.inbox).context("Failed to read inbox directory")? {
-            let entry = entry.context("Failed to read directory entry")?;
-            let path = entry.path();
-
-            if let Some(last_modified) = self.get_last_modified_time(&path)? {
-                if last_modified < cutoff_date {
-                    let age_days = (Utc::now() - last_modified).num_days();
-                    let name = path
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("unknown")
-                        .to_string();
+        for inbox in self.config.inboxes() {
+            for mut item in self.find_items_past(&inbox, Threshold::AutoArchive)? {
+                if item.policy.action == RuleAction::Keep
+                    || state.hold(&inbox.name, &item.identity).is_some()
+                {
+                    continue;
+                }
+                item.load_notifications(&state);
 
-                    let notification_count = state.get_notification_count(&name);
-
-                    // Only eligible if it has been notified enough times
-                    if notification_count >= self.config.auto_archive_min_scans {
-                        auto_archive_items.push(StaleItem {
-                            path: path.clone(),
-                            name,
-                            last_modified,
-                            is_directory: path.is_dir(),
-                            age_days,
-                            notification_count,
-                        });
-                    }
+                // Only eligible once it has been notified on enough days
+                if item.days_needed() == 0 {
+                    auto_archive_items.push(item);
                 }
             }
         }
@@ -159,21 +187,60 @@ impl Scanner {
         Ok(auto_archive_items)
     }
 
-    // New method to get items that will be eligible after more scans
+    // New method to get items that will be eligible once notified on more days
     pub fn scan_pending_auto_archive(&self) -> Result<Vec<StaleItem>> {
-        if !self.config.inbox.exists() {
+        let state = NotificationState::load().unwrap_or_default();
+        let mut pending_items = Vec::new();
+
+        for inbox in self.config.inboxes() {
+            for mut item in self.find_items_past(&inbox, Threshold::AutoArchive)? {
+                if item.policy.action == RuleAction::Keep
+                    || state.hold(&inbox.name, &item.identity).is_some()
+                {
+                    continue;
+                }
+                item.load_notifications(&state);
+
+                // Pending if old enough but not notified on enough days yet
+                if item.days_needed() > 0 {
+                    pending_items.push(item);
+                }
+            }
+        }
+
+        Ok(pending_items)
+    }
+
+    /// Top-level entries of `inbox` older than their `threshold`, as set by the
+    /// inbox or by the first matching rule, skipping anything excluded by the
+    /// inbox's ignore rules. The returned items carry a notification count of zero.
+    fn find_items_past(&self, inbox: &Inbox, threshold: Threshold) -> Result<Vec<StaleItem>> {
+        if !inbox.path.exists() {
             return Ok(vec![]);
         }
 
-        let state = NotificationState::load().unwrap_or_default();
-        let mut pending_items = Vec::new();
-        let cutoff_date =
-            Utc::now() - Duration::days(self.config.auto_archive_threshold_days as i64);
+        let mut items = Vec::new();
+        let ignore_rules = IgnoreRules::for_inbox(&self.config, &inbox.path)?;
+        let rules = RuleSet::new(&self.config)?;
 
-        for entry in fs::read_dir(&self.config.inbox).context("Failed to read inbox directory")? {
+        for entry in fs::read_dir(&inbox.path).context(format!(
+            "Failed to read inbox directory: {}",
+            inbox.path.display()
+        ))? {
             let entry = entry.context("Failed to read directory entry")?;
             let path = entry.path();
 
+            if ignore_rules.is_ignored(&path) {
+                continue;
+            }
+
+            let policy = rules.policy_for(inbox, &path)?;
+            let threshold_days = match threshold {
+                Threshold::Stale => policy.age_threshold_days,
+                Threshold::AutoArchive => policy.auto_archive_threshold_days,
+            };
+            let cutoff_date = Utc::now() - Duration::days(threshold_days as i64);
+
             if let Some(last_modified) = self.get_last_modified_time(&path)? {
                 if last_modified < cutoff_date {
                     let age_days = (Utc::now() - last_modified).num_days();
@@ -183,24 +250,35 @@ impl Scanner {
                         .unwrap_or("unknown")
                         .to_string();
 
-                    let notification_count = state.get_notification_count(&name);
-
-                    // Pending if old enough but not notified enough times yet
-                    if notification_count < self.config.auto_archive_min_scans {
-                        pending_items.push(StaleItem {
-                            path: path.clone(),
-                            name,
-                            last_modified,
-                            is_directory: path.is_dir(),
-                            age_days,
-                            notification_count,
-                        });
-                    }
+                    items.push(StaleItem {
+                        identity: FileIdentity::of(&name, &path),
+                        path: path.clone(),
+                        name,
+                        inbox: inbox.name.clone(),
+                        last_modified,
+                        is_directory: path.is_dir(),
+                        age_days,
+                        notification_count: 0,
+                        first_seen: None,
+                        notified: NotificationSpread::default(),
+                        policy,
+                    });
                 }
             }
         }
 
-        Ok(pending_items)
+        Ok(items)
+    }
+
+    /// Identities of every top-level entry of `inbox`, stale or not.
+    fn inbox_identities(inbox: &Inbox) -> Vec<FileIdentity> {
+        let Ok(entries) = fs::read_dir(&inbox.path) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| FileIdentity::of(&entry.file_name().to_string_lossy(), &entry.path()))
+            .collect()
     }
 
     fn get_last_modified_time(&self, path: &Path) -> Result<Option<DateTime<Utc>>> {
@@ -245,6 +323,37 @@ impl Scanner {
         }
     }
 
+    /// Stale items together with the pending and eligible auto-archive items,
+    /// each tagged with its category.
+    pub fn scan_records(&self, stale_items: &[StaleItem]) -> Result<Vec<ScanRecord>> {
+        let mut records: Vec<ScanRecord> = stale_items
+            .iter()
+            .map(|item| ScanRecord {
+                category: ScanCategory::Stale,
+                item: item.clone(),
+                days_needed: None,
+            })
+            .collect();
+
+        for item in self.scan_pending_auto_archive()? {
+            records.push(ScanRecord {
+                category: ScanCategory::Pending,
+                days_needed: Some(item.days_needed()),
+                item,
+            });
+        }
+
+        for item in self.scan_auto_archive_eligible()? {
+            records.push(ScanRecord {
+                category: ScanCategory::Eligible,
+                item,
+                days_needed: None,
+            });
+        }
+
+        Ok(records)
+    }
+
     pub fn display_scan_results(&self, stale_items: &[StaleItem]) {
         // Check for auto-archive eligible and pending items
         let auto_archive_items = self.scan_auto_archive_eligible().unwrap_or_default();
@@ -259,6 +368,80 @@ impl Scanner {
             return;
         }
 
+        let inboxes = self.config.inboxes();
+        let show_inbox_headers = inboxes.len() > 1;
+        let mut first_inbox = true;
+
+        for inbox in &inboxes {
+            let inbox_stale: Vec<&StaleItem> = stale_items
+                .iter()
+                .filter(|item| item.inbox == inbox.name)
+                .collect();
+            let inbox_pending: Vec<&StaleItem> = pending_items
+                .iter()
+                .filter(|item| item.inbox == inbox.name)
+                .collect();
+            let inbox_auto_archive: Vec<&StaleItem> = auto_archive_items
+                .iter()
+                .filter(|item| item.inbox == inbox.name)
+                .collect();
+
+            if inbox_stale.is_empty() && inbox_pending.is_empty() && inbox_auto_archive.is_empty() {
+                continue;
+            }
+
+            if show_inbox_headers {
+                if !first_inbox {
+                    println!();
+                }
+                println!("📂 {} ({}):", inbox.name, inbox.path.display());
+            }
+            first_inbox = false;
+
+            self.display_inbox_results(inbox, &inbox_stale, &inbox_pending, &inbox_auto_archive);
+        }
+
+        println!("\n💡 Run 'relfa review' to interactively deal with these items");
+        if !stale_items.is_empty() && !auto_archive_items.is_empty() {
+            println!(
+                "   or 'relfa archive' to auto-archive old files (or 'relfa archive --all' for all)."
+            );
+        } else if !stale_items.is_empty() {
+            println!("   or 'relfa archive --all' to archive them all to the Graveyard.");
+        } else if !auto_archive_items.is_empty() {
+            println!("   or 'relfa archive' to auto-archive old files.");
+        }
+
+        // Send desktop notification
+        let total_items = stale_items.len() + auto_archive_items.len();
+        let notification_text = if !auto_archive_items.is_empty() {
+            format!(
+                "{} items need attention in Inbox. {} are eligible for auto-archiving!",
+                total_items,
+                auto_archive_items.len()
+            )
+        } else {
+            format!(
+                "{} {} in Inbox {} gathering dust. Consider reviewing them!",
+                stale_items.len(),
+                if stale_items.len() == 1 {
+                    "item"
+                } else {
+                    "items"
+                },
+                if stale_items.len() == 1 { "is" } else { "are" }
+            )
+        };
+        self.send_notification("Digital Clutter Detected", &notification_text);
+    }
+
+    fn display_inbox_results(
+        &self,
+        inbox: &Inbox,
+        stale_items: &[&StaleItem],
+        pending_items: &[&StaleItem],
+        auto_archive_items: &[&StaleItem],
+    ) {
         // Display stale items (regular threshold)
         if !stale_items.is_empty() {
             let plural = if stale_items.len() == 1 {
@@ -267,19 +450,23 @@ impl Scanner {
                 "items"
             };
             let message = format!(
-                "☠️  {} {} in ~/Inbox {} gathering dust:",
+                "☠️  {} {} in {} {} gathering dust:",
                 stale_items.len(),
                 plural,
+                inbox.path.display(),
                 if stale_items.len() == 1 { "is" } else { "are" }
             );
 
             println!("{message}");
 
             for item in stale_items {
-                let scan_info = if item.notification_count > 0 {
-                    format!(" [seen {} times]", item.notification_count)
-                } else {
-                    String::new()
+                let scan_info = match (item.notification_count, item.first_seen) {
+                    (0, _) => String::new(),
+                    (count, Some(first_seen)) => format!(
+                        " [seen {count} times since {}]",
+                        first_seen.format("%Y-%m-%d")
+                    ),
+                    (count, None) => format!(" [seen {count} times]"),
                 };
                 println!("   {}{}", item.display(), scan_info);
             }
@@ -292,7 +479,7 @@ impl Scanner {
             }
 
             println!(
-                "⏳ {} {} old enough for auto-archiving but {} more scans:",
+                "⏳ {} {} old enough for auto-archiving but {} notifying on more days:",
                 pending_items.len(),
                 if pending_items.len() == 1 {
                     "item"
@@ -306,13 +493,13 @@ impl Scanner {
                 }
             );
 
-            for item in &pending_items {
-                let scans_needed = self.config.auto_archive_min_scans - item.notification_count;
+            for item in pending_items {
+                let days_needed = item.days_needed();
                 println!(
-                    "   {} [needs {} more {}]",
+                    "   {} [needs notifying on {} more {}]",
                     item.display(),
-                    scans_needed,
-                    if scans_needed == 1 { "scan" } else { "scans" }
+                    days_needed,
+                    if days_needed == 1 { "day" } else { "days" }
                 );
             }
         }
@@ -340,51 +527,39 @@ impl Scanner {
                 }
             );
 
-            for item in &auto_archive_items {
+            for item in auto_archive_items {
+                let action_info = match (&item.policy.action, &item.policy.rule) {
+                    (RuleAction::Delete, Some(rule)) => format!(", will be deleted by rule {rule}"),
+                    (RuleAction::Delete, None) => ", will be deleted".to_string(),
+                    _ => String::new(),
+                };
                 println!(
-                    "   {} [notified {} times]",
+                    "   {} [notified {} times on {} {}{}]",
                     item.display(),
-                    item.notification_count
+                    item.notification_count,
+                    item.notified.days,
+                    if item.notified.days == 1 {
+                        "day"
+                    } else {
+                        "days"
+                    },
+                    action_info
                 );
             }
 
-            println!(
-                "   ⚠️  These will be automatically archived if you run 'relfa archive' without arguments!"
-            );
-        }
-
-        println!("\n💡 Run 'relfa review' to interactively deal with these items");
-        if !stale_items.is_empty() && !auto_archive_items.is_empty() {
-            println!(
-                "   or 'relfa archive' to auto-archive old files (or 'relfa archive --all' for all)."
-            );
-        } else if !stale_items.is_empty() {
-            println!("   or 'relfa archive --all' to archive them all to the Graveyard.");
-        } else if !auto_archive_items.is_empty() {
-            println!("   or 'relfa archive' to auto-archive old files.");
+            if auto_archive_items
+                .iter()
+                .any(|item| item.policy.action == RuleAction::Delete)
+            {
+                println!(
+                    "   ⚠️  These will be automatically archived or deleted if you run 'relfa archive' without arguments!"
+                );
+            } else {
+                println!(
+                    "   ⚠️  These will be automatically archived if you run 'relfa archive' without arguments!"
+                );
+            }
         }
-
-        // Send desktop notification
-        let total_items = stale_items.len() + auto_archive_items.len();
-        let notification_text = if !auto_archive_items.is_empty() {
-            format!(
-                "{} items need attention in Inbox. {} are eligible for auto-archiving!",
-                total_items,
-                auto_archive_items.len()
-            )
-        } else {
-            format!(
-                "{} {} in Inbox {} gathering dust. Consider reviewing them!",
-                stale_items.len(),
-                if stale_items.len() == 1 {
-                    "item"
-                } else {
-                    "items"
-                },
-                if stale_items.len() == 1 { "is" } else { "are" }
-            )
-        };
-        self.send_notification("Digital Clutter Detected", &notification_text);
     }
 
     fn send_notification(&self, title: &str, body: &str) {
@@ -1,14 +1,196 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, DEFAULT_INBOX_NAME};
+use crate::utils::write_atomic;
+
+/// What tells one inbox item apart from another, even after a rename: its
+/// device and inode, size and modification time. The name is only a hint.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileIdentity {
+    /// Name relative to the inbox when the item was last seen
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inode: Option<u64>,
+    #[serde(default)]
+    pub size: u64,
+    /// Unset for counts recorded before items were tracked by identity
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<DateTime<Utc>>,
+}
+
+impl FileIdentity {
+    /// The identity of the inbox item called `name` at `path`.
+    pub fn of(name: &str, path: &Path) -> Self {
+        let mut identity = Self {
+            name: name.to_string(),
+            ..Self::default()
+        };
+        let Ok(metadata) = fs::metadata(path).or_else(|_| fs::symlink_metadata(path)) else {
+            return identity;
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            identity.device = Some(metadata.dev());
+            identity.inode = Some(metadata.ino());
+        }
+        identity.size = metadata.len();
+        identity.modified = metadata.modified().ok().map(DateTime::from);
+        identity
+    }
+
+    /// Whether `self` and `other` are the same file, as unchanged since it was
+    /// recorded. Inodes are reused, so a new file in the same spot only
+    /// matches if it also has the same size and modification time.
+    fn same_file(&self, other: &Self) -> bool {
+        // Legacy counts only knew the name
+        if self.modified.is_none() || other.modified.is_none() {
+            return self.name == other.name;
+        }
+
+        let same_content = self.size == other.size && self.modified == other.modified;
+        match (self.device.zip(self.inode), other.device.zip(other.inode)) {
+            (Some(ours), Some(theirs)) => ours == theirs && same_content,
+            _ => self.name == other.name && same_content,
+        }
+    }
+
+    /// Whether `self` and `other` are the same file, even if it has been
+    /// changed since. Pins and snoozes follow the file, not its contents.
+    fn same_inode(&self, other: &Self) -> bool {
+        match (self.device.zip(self.inode), other.device.zip(other.inode)) {
+            (Some(ours), Some(theirs)) => ours == theirs,
+            _ => self.name == other.name,
+        }
+    }
+}
+
+/// Notifications recorded for a single inbox item.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackedItem {
+    #[serde(flatten)]
+    pub identity: FileIdentity,
+    pub count: u32,
+    /// When a scan first found the item stale, unknown for legacy counts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_seen: Option<DateTime<Utc>>,
+    /// When the last notification of each local day was sent, oldest first;
+    /// legacy counts have none
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notified: Vec<DateTime<Utc>>,
+    /// Scans leave the item alone for good
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// Scans leave the item alone until then
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snoozed_until: Option<DateTime<Utc>>,
+}
+
+/// Why scans currently leave an inbox item alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hold {
+    Pinned,
+    SnoozedUntil(DateTime<Utc>),
+}
+
+impl Hold {
+    pub fn describe(&self) -> String {
+        match self {
+            Hold::Pinned => "pinned".to_string(),
+            Hold::SnoozedUntil(until) => format!(
+                "snoozed until {}",
+                until.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+            ),
+        }
+    }
+}
+
+/// How an item's notifications are spread over time, which is what
+/// auto-archiving waits on rather than how often `scan` happened to run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct NotificationSpread {
+    /// Distinct local days with at least one notification
+    pub days: u32,
+    /// Days from the first notified day to the last
+    pub span_days: u32,
+}
+
+impl TrackedItem {
+    fn new(identity: &FileIdentity) -> Self {
+        Self {
+            identity: identity.clone(),
+            count: 0,
+            first_seen: None,
+            notified: Vec::new(),
+            pinned: false,
+            snoozed_until: None,
+        }
+    }
+
+    /// The pin or snooze in effect, if any.
+    pub fn hold(&self) -> Option<Hold> {
+        if self.pinned {
+            return Some(Hold::Pinned);
+        }
+        self.snoozed_until
+            .filter(|until| *until > Utc::now())
+            .map(Hold::SnoozedUntil)
+    }
+
+    pub fn spread(&self) -> NotificationSpread {
+        let mut days: Vec<NaiveDate> = self.notified.iter().map(local_day).collect();
+        // The clock may have gone backwards between notifications
+        days.sort();
+        days.dedup();
+
+        NotificationSpread {
+            days: days.len() as u32,
+            span_days: match (days.first(), days.last()) {
+                (Some(first), Some(last)) => (*last - *first).num_days() as u32,
+                _ => 0,
+            },
+        }
+    }
+
+    /// Record a notification sent at `time`, keeping one per local day so
+    /// frequent scans don't grow the state.
+    fn record_notification(&mut self, time: DateTime<Utc>) {
+        self.notified.push(time);
+        self.compact_notified();
+    }
+
+    /// Sort the notification times and keep the last one of each local day.
+    fn compact_notified(&mut self) {
+        self.notified.sort();
+        self.notified.reverse();
+        self.notified.dedup_by_key(|time| local_day(time));
+        self.notified.reverse();
+    }
+}
+
+fn local_day(time: &DateTime<Utc>) -> NaiveDate {
+    time.with_timezone(&Local).date_naive()
+}
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct NotificationState {
-    // Map from file path (relative to inbox) to notification count
+    /// Tracked items of each inbox, by inbox name
     #[serde(default)]
-    pub notification_counts: HashMap<String, u32>,
+    pub items: HashMap<String, Vec<TrackedItem>>,
+    // Counts keyed by file name, written before items were tracked by identity
+    #[serde(default, skip_serializing)]
+    inboxes: HashMap<String, HashMap<String, u32>>,
+    // Counts written before multiple inboxes were supported; they belong to the default inbox
+    #[serde(default, skip_serializing)]
+    notification_counts: HashMap<String, u32>,
 }
 
 impl NotificationState {
@@ -19,7 +201,43 @@ impl NotificationState {
             let content = fs::read_to_string(&state_path)
                 .context("Failed to read notification state file")?;
 
-            toml::from_str(&content).context("Failed to parse notification state file")
+            let mut state: Self =
+                toml::from_str(&content).context("Failed to parse notification state file")?;
+
+            if !state.notification_counts.is_empty() {
+                let legacy = std::mem::take(&mut state.notification_counts);
+                let counts = state
+                    .inboxes
+                    .entry(DEFAULT_INBOX_NAME.to_string())
+                    .or_default();
+                for (file_name, count) in legacy {
+                    counts.entry(file_name).or_insert(count);
+                }
+            }
+
+            // States written before notifications were kept per day shrink on load
+            for item in state.items.values_mut().flatten() {
+                item.compact_notified();
+            }
+
+            // Name-keyed counts match by name until their item is seen again
+            for (inbox, counts) in std::mem::take(&mut state.inboxes) {
+                let items = state.items.entry(inbox).or_default();
+                for (name, count) in counts {
+                    let identity = FileIdentity {
+                        name,
+                        ..FileIdentity::default()
+                    };
+                    if !items.iter().any(|item| item.identity.same_file(&identity)) {
+                        items.push(TrackedItem {
+                            count,
+                            ..TrackedItem::new(&identity)
+                        });
+                    }
+                }
+            }
+
+            Ok(state)
         } else {
             Ok(Self::default())
         }
@@ -35,55 +253,293 @@ impl NotificationState {
         let content =
             toml::to_string_pretty(self).context("Failed to serialize notification state")?;
 
-        fs::write(&state_path, content).context("Failed to write notification state file")?;
-
-        Ok(())
+        write_atomic(&state_path, &content).context("Failed to write notification state file")
     }
 
-    fn state_path() -> PathBuf {
-        // Try XDG_STATE_HOME first (defaults to ~/.local/state)
-        if let Ok(state_home) = std::env::var("XDG_STATE_HOME") {
-            PathBuf::from(state_home)
-                .join("relfa")
-                .join("notification_state.toml")
-        } else if let Some(home_dir) = dirs::home_dir() {
-            // Use the XDG default location
-            home_dir
-                .join(".local")
-                .join("state")
-                .join("relfa")
-                .join("notification_state.toml")
+    /// Save the state, or only describe the pending `change` in dry-run mode.
+    pub fn save_unless_dry_run(&self, config: &Config, change: &str) -> Result<()> {
+        if config.dry_run {
+            if config.output.is_text() {
+                println!("🔍 Would update notification state: {change}");
+            }
+            Ok(())
         } else {
-            // Fallback to same directory as config file
-            dirs::config_dir()
-                .map(|d| d.join("relfa").join("notification_state.toml"))
-                .unwrap_or_else(|| PathBuf::from("relfa").join("notification_state.toml"))
+            self.save()
         }
     }
 
-    pub fn get_notification_count(&self, file_name: &str) -> u32 {
-        self.notification_counts
-            .get(file_name)
-            .copied()
+    fn state_path() -> PathBuf {
+        state_dir().join("notification_state.toml")
+    }
+
+    fn find(&self, inbox: &str, identity: &FileIdentity) -> Option<&TrackedItem> {
+        self.items
+            .get(inbox)?
+            .iter()
+            .find(|item| item.identity.same_file(identity))
+    }
+
+    fn find_mut(&mut self, inbox: &str, identity: &FileIdentity) -> Option<&mut TrackedItem> {
+        self.items
+            .get_mut(inbox)?
+            .iter_mut()
+            .find(|item| item.identity.same_file(identity))
+    }
+
+    pub fn get_notification_count(&self, inbox: &str, identity: &FileIdentity) -> u32 {
+        self.find(inbox, identity)
+            .map(|item| item.count)
             .unwrap_or(0)
     }
 
-    pub fn increment_notification_count(&mut self, file_name: &str) {
-        let count = self
-            .notification_counts
-            .entry(file_name.to_string())
-            .or_insert(0);
-        *count += 1;
+    /// What is known about the item's notifications, if it has been notified about.
+    pub fn tracked(&self, inbox: &str, identity: &FileIdentity) -> Option<&TrackedItem> {
+        self.find(inbox, identity)
+    }
+
+    /// When a scan first found the item stale, if it has been notified about.
+    pub fn first_seen(&self, inbox: &str, identity: &FileIdentity) -> Option<DateTime<Utc>> {
+        self.find(inbox, identity)?.first_seen
+    }
+
+    /// The days on which the item has been notified about.
+    pub fn notification_spread(&self, inbox: &str, identity: &FileIdentity) -> NotificationSpread {
+        self.find(inbox, identity)
+            .map(TrackedItem::spread)
+            .unwrap_or_default()
+    }
+
+    pub fn increment_notification_count(&mut self, inbox: &str, identity: &FileIdentity) {
+        let now = Utc::now();
+        match self.find_mut(inbox, identity) {
+            Some(item) => {
+                item.count += 1;
+                item.record_notification(now);
+                // Legacy entries learn who they are, renamed items their new name.
+                // When they were first seen is lost, so it stays unknown
+                item.identity = identity.clone();
+            }
+            None => self
+                .items
+                .entry(inbox.to_string())
+                .or_default()
+                .push(TrackedItem {
+                    count: 1,
+                    first_seen: Some(now),
+                    notified: vec![now],
+                    ..TrackedItem::new(identity)
+                }),
+        }
+    }
+
+    /// Put back an item's notifications as they were before it was archived
+    /// or touched, when that is undone.
+    pub fn restore_notifications(
+        &mut self,
+        inbox: &str,
+        identity: &FileIdentity,
+        count: u32,
+        first_seen: Option<DateTime<Utc>>,
+        notified: Vec<DateTime<Utc>>,
+    ) {
+        if count == 0 {
+            self.reset_notification_count(inbox, identity);
+            return;
+        }
+        let item = match self.find_mut(inbox, identity) {
+            Some(item) => item,
+            None => {
+                let items = self.items.entry(inbox.to_string()).or_default();
+                items.push(TrackedItem::new(identity));
+                items.last_mut().expect("item was just pushed")
+            }
+        };
+        item.count = count;
+        item.first_seen = first_seen;
+        item.notified = notified;
+    }
+
+    pub fn reset_notification_count(&mut self, inbox: &str, identity: &FileIdentity) {
+        if let Some(items) = self.items.get_mut(inbox) {
+            items.retain(|item| !item.identity.same_file(identity));
+        }
+    }
+
+    /// The pin or snooze keeping scans away from the item, if any.
+    pub fn hold(&self, inbox: &str, identity: &FileIdentity) -> Option<Hold> {
+        self.items
+            .get(inbox)?
+            .iter()
+            .filter(|item| item.identity.same_inode(identity))
+            .find_map(TrackedItem::hold)
+    }
+
+    /// Pin or snooze the item, or lift its pin or snooze with `None`. Either
+    /// way its notifications start over.
+    pub fn set_hold(&mut self, inbox: &str, identity: &FileIdentity, hold: Option<Hold>) {
+        let items = self.items.entry(inbox.to_string()).or_default();
+        items.retain(|item| !item.identity.same_inode(identity));
+
+        if let Some(hold) = hold {
+            let mut item = TrackedItem::new(identity);
+            match hold {
+                Hold::Pinned => item.pinned = true,
+                Hold::SnoozedUntil(until) => item.snoozed_until = Some(until),
+            }
+            items.push(item);
+        }
+    }
+
+    /// Every pinned or snoozed item, by inbox name.
+    pub fn holds(&self) -> Vec<(&str, &TrackedItem, Hold)> {
+        let mut holds: Vec<_> = self
+            .items
+            .iter()
+            .flat_map(|(inbox, items)| {
+                items
+                    .iter()
+                    .filter_map(move |item| Some((inbox.as_str(), item, item.hold()?)))
+            })
+            .collect();
+        holds.sort_by(|a, b| (a.0, &a.1.identity.name).cmp(&(b.0, &b.1.identity.name)));
+        holds
     }
 
-    pub fn reset_notification_count(&mut self, file_name: &str) {
-        self.notification_counts.remove(file_name);
+    /// Keep only the entries of files in `stale_files`, and the pins and
+    /// snoozes of files still in the inbox (`present_files`). This removes
+    /// entries for files that are no longer stale.
+    pub fn retain_only_files(
+        &mut self,
+        inbox: &str,
+        stale_files: &[FileIdentity],
+        present_files: &[FileIdentity],
+    ) {
+        if let Some(items) = self.items.get_mut(inbox) {
+            items.retain(|item| {
+                stale_files
+                    .iter()
+                    .any(|identity| item.identity.same_file(identity))
+                    || (item.hold().is_some()
+                        && present_files
+                            .iter()
+                            .any(|identity| item.identity.same_inode(identity)))
+            });
+        }
+    }
+
+    pub fn retain_only_inboxes(&mut self, inboxes: &std::collections::HashSet<String>) {
+        // Drop counts for inboxes that were removed from the config
+        self.items.retain(|name, _| inboxes.contains(name));
+    }
+}
+
+/// Directory holding relfa's mutable state (notification counts, journal).
+pub fn state_dir() -> PathBuf {
+    // Try XDG_STATE_HOME first (defaults to ~/.local/state)
+    if let Ok(state_home) = std::env::var("XDG_STATE_HOME") {
+        PathBuf::from(state_home).join("relfa")
+    } else if let Some(home_dir) = dirs::home_dir() {
+        // Use the XDG default location
+        home_dir.join(".local").join("state").join("relfa")
+    } else {
+        // Fallback to same directory as config file
+        dirs::config_dir()
+            .map(|d| d.join("relfa"))
+            .unwrap_or_else(|| PathBuf::from("relfa"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    /// A time on the given local day, away from midnight and DST changes.
+    fn on_day(day: u32, hour: u32) -> DateTime<Utc> {
+        Local
+            .with_ymd_and_hms(2024, 3, day, hour, 0, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn tracked(notified: Vec<DateTime<Utc>>) -> TrackedItem {
+        TrackedItem {
+            notified,
+            ..TrackedItem::new(&FileIdentity::default())
+        }
     }
 
-    pub fn retain_only_files(&mut self, current_files: &std::collections::HashSet<String>) {
-        // Keep only the files that are in the current_files set
-        // This removes entries for files that are no longer stale
-        self.notification_counts
-            .retain(|file_name, _| current_files.contains(file_name));
+    #[test]
+    fn spread_of_an_item_never_notified() {
+        assert_eq!(tracked(Vec::new()).spread(), NotificationSpread::default());
+    }
+
+    #[test]
+    fn spread_counts_days_and_their_span() {
+        let item = tracked(vec![
+            on_day(1, 10),
+            on_day(1, 18),
+            on_day(2, 9),
+            on_day(5, 12),
+        ]);
+
+        assert_eq!(
+            item.spread(),
+            NotificationSpread {
+                days: 3,
+                span_days: 4
+            }
+        );
+    }
+
+    #[test]
+    fn spread_ignores_the_order_of_notifications() {
+        // Left out of order by a clock that went backwards
+        let item = tracked(vec![
+            on_day(5, 12),
+            on_day(1, 10),
+            on_day(5, 8),
+            on_day(1, 9),
+        ]);
+
+        assert_eq!(
+            item.spread(),
+            NotificationSpread {
+                days: 2,
+                span_days: 4
+            }
+        );
+    }
+
+    #[test]
+    fn keeps_one_notification_per_day() {
+        let mut item = tracked(Vec::new());
+        for hour in [9, 12, 15] {
+            item.record_notification(on_day(1, hour));
+        }
+        item.record_notification(on_day(3, 8));
+        item.record_notification(on_day(2, 8));
+
+        assert_eq!(
+            item.notified,
+            vec![on_day(1, 15), on_day(2, 8), on_day(3, 8)]
+        );
+        assert_eq!(item.spread().days, 3);
+    }
+
+    #[test]
+    fn counts_every_notification() {
+        let identity = FileIdentity {
+            name: "report.pdf".to_string(),
+            modified: Some(Utc::now() - Duration::days(10)),
+            ..FileIdentity::default()
+        };
+        let mut state = NotificationState::default();
+        state.increment_notification_count("downloads", &identity);
+        state.increment_notification_count("downloads", &identity);
+
+        assert_eq!(state.get_notification_count("downloads", &identity), 2);
+        assert_eq!(state.notification_spread("downloads", &identity).days, 1);
+        assert!(state.first_seen("downloads", &identity).is_some());
     }
 }
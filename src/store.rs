@@ -0,0 +1,258 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::config::{Config, DedupMode};
+use crate::utils::{
+    canonical_location, copy_symlink, copy_times, create_relative_symlink, item_hash,
+    remove_empty_parents, symlink_target,
+};
+
+pub const STORE_DIR_NAME: &str = ".relfa-store";
+
+/// Content-addressed store inside the Graveyard. Every distinct file content
+/// is kept once as `.relfa-store/<aa>/<sha256>`, and archived files become
+/// hardlinks or symlinks to it.
+pub struct ContentStore {
+    root: PathBuf,
+    mode: DedupMode,
+}
+
+impl ContentStore {
+    /// The store configured for the Graveyard, or `None` when dedup is off.
+    pub fn new(config: &Config) -> Option<Self> {
+        if config.dedup.is_off() {
+            return None;
+        }
+        Some(Self {
+            root: Self::root(config),
+            mode: config.dedup,
+        })
+    }
+
+    pub fn root(config: &Config) -> PathBuf {
+        config.graveyard.join(STORE_DIR_NAME)
+    }
+
+    /// Whether `path` is a symlink into the store, i.e. a stored item in symlink mode.
+    pub fn is_store_link(config: &Config, path: &Path) -> bool {
+        let root = canonical_location(&Self::root(config));
+        symlink_target(path).is_some_and(|target| canonical_location(&target).starts_with(&root))
+    }
+
+    /// Whether the symlink at `link` points at a blob of any content store,
+    /// judging by the `.relfa-store/<aa>/<sha256>` shape of its target.
+    pub fn is_blob(link: &Path) -> bool {
+        let Some(target) = symlink_target(link) else {
+            return false;
+        };
+        let store = target.parent().and_then(Path::parent);
+        store.and_then(Path::file_name) == Some(STORE_DIR_NAME.as_ref())
+            && target
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .is_some_and(|name| name.len() == 64 && name.chars().all(|c| c.is_ascii_hexdigit()))
+    }
+
+    /// Move the content of every file at or under `path` into the store,
+    /// leaving a link behind. Returns the bytes saved by content that was
+    /// already stored.
+    pub fn absorb(&self, path: &Path) -> Result<u64> {
+        if !path.is_dir() {
+            return self.absorb_file(path);
+        }
+
+        let mut saved = 0;
+        for entry in WalkDir::new(path) {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                saved += self.absorb_file(entry.path())?;
+            }
+        }
+        Ok(saved)
+    }
+
+    fn absorb_file(&self, file: &Path) -> Result<u64> {
+        if file.is_symlink() {
+            return Ok(0);
+        }
+
+        let hash = item_hash(file)?;
+        let blob = self.root.join(&hash[..2]).join(&hash);
+
+        if !blob.exists() {
+            fs::create_dir_all(blob.parent().unwrap())
+                .context("Failed to create content store directory")?;
+            match self.mode {
+                DedupMode::Hardlink => {
+                    // Filesystems without hardlinks simply keep the plain copy
+                    if fs::hard_link(file, &blob).is_err() {
+                        return Ok(0);
+                    }
+                }
+                DedupMode::Symlink => {
+                    fs::rename(file, &blob).context("Failed to move file into content store")?;
+                    create_relative_symlink(&blob, file)?;
+                }
+                DedupMode::Off => {}
+            }
+            return Ok(0);
+        }
+
+        // Build the link next to the file, then swap it in place atomically
+        let size = fs::metadata(file)?.len();
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let temp = file.with_file_name(format!(".{name}.relfa-link"));
+        match self.mode {
+            DedupMode::Hardlink => {
+                if fs::hard_link(&blob, &temp).is_err() {
+                    return Ok(0);
+                }
+            }
+            DedupMode::Symlink => create_relative_symlink(&blob, &temp)?,
+            DedupMode::Off => return Ok(0),
+        }
+        fs::rename(&temp, file).context("Failed to replace file with a content store link")?;
+
+        Ok(size)
+    }
+
+    /// Recreate `source`, whose content was already absorbed, at `dest` by
+    /// linking every file to its blob instead of copying it. Returns the bytes
+    /// that did not have to be written.
+    pub fn link_copy(&self, source: &Path, dest: &Path) -> Result<u64> {
+        let mut saved = 0;
+        for entry in WalkDir::new(source) {
+            let entry = entry?;
+            let relative = entry.path().strip_prefix(source).unwrap_or(entry.path());
+            let target = if relative.as_os_str().is_empty() {
+                dest.to_path_buf()
+            } else {
+                dest.join(relative)
+            };
+
+            if entry.file_type().is_dir() {
+                fs::create_dir_all(&target).context("Failed to create directory")?;
+            } else if entry.path_is_symlink() {
+                match symlink_target(entry.path()) {
+                    // Point at the blob from the new location
+                    Some(blob) if Self::is_blob(entry.path()) => {
+                        create_relative_symlink(&blob, &target)?;
+                        saved += fs::metadata(&blob)?.len();
+                    }
+                    _ => copy_symlink(entry.path(), &target)?,
+                }
+            } else if fs::hard_link(entry.path(), &target).is_ok() {
+                saved += entry.metadata()?.len();
+            } else {
+                // Files the store could not take in are copied as before
+                fs::copy(entry.path(), &target).context("Failed to copy file")?;
+            }
+        }
+        Ok(saved)
+    }
+
+    /// Replace links into the store at or under `path` with real copies, so
+    /// the item survives being moved out of the Graveyard without sharing
+    /// content with what stays behind.
+    pub fn materialize(config: &Config, path: &Path) -> Result<()> {
+        if !Self::root(config).exists() {
+            return Ok(());
+        }
+
+        for entry in WalkDir::new(path) {
+            let entry = entry?;
+            let source = if entry.path_is_symlink() {
+                if !Self::is_store_link(config, entry.path()) {
+                    continue;
+                }
+                symlink_target(entry.path()).unwrap()
+            } else if entry.file_type().is_file() && link_count(&entry.metadata()?) > 1 {
+                entry.path().to_path_buf()
+            } else {
+                continue;
+            };
+
+            let name = entry.file_name().to_string_lossy();
+            let temp = entry.path().with_file_name(format!(".{name}.relfa-copy"));
+            fs::copy(&source, &temp).context("Failed to copy file out of content store")?;
+            copy_times(&source, &temp)?;
+            fs::rename(&temp, entry.path())
+                .context("Failed to replace content store link with a copy")?;
+        }
+        Ok(())
+    }
+
+    /// Remove blobs nothing in the Graveyard links to anymore. Returns the bytes freed.
+    pub fn prune(config: &Config) -> Result<u64> {
+        if config.dry_run {
+            return Ok(0);
+        }
+
+        let root = Self::root(config);
+        let mut freed = 0;
+        for blob in Self::unreferenced_blobs(config)? {
+            let size = fs::metadata(&blob)?.len();
+            fs::remove_file(&blob).context("Failed to remove unused content store blob")?;
+            remove_empty_parents(&blob, &root);
+            freed += size;
+        }
+        Ok(freed)
+    }
+
+    /// Every blob in the store.
+    pub fn blobs(config: &Config) -> Vec<PathBuf> {
+        WalkDir::new(Self::root(config))
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path())
+            .collect()
+    }
+
+    /// Blobs that no stored file links to.
+    pub fn unreferenced_blobs(config: &Config) -> Result<Vec<PathBuf>> {
+        let root = Self::root(config);
+        if !root.exists() {
+            return Ok(Vec::new());
+        }
+
+        // Blobs referenced through symlinks; hardlinked ones are counted by the filesystem
+        let mut linked = HashSet::new();
+        for entry in WalkDir::new(&config.graveyard)
+            .into_iter()
+            .filter_entry(|entry| entry.path() != root)
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path_is_symlink())
+        {
+            if let Some(target) = symlink_target(entry.path()) {
+                linked.insert(canonical_location(&target));
+            }
+        }
+
+        let mut unreferenced = Vec::new();
+        for blob in Self::blobs(config) {
+            if linked.contains(&canonical_location(&blob)) {
+                continue;
+            }
+            if link_count(&fs::metadata(&blob)?) <= 1 {
+                unreferenced.push(blob);
+            }
+        }
+        Ok(unreferenced)
+    }
+}
+
+#[cfg(unix)]
+fn link_count(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink()
+}
+
+#[cfg(not(unix))]
+fn link_count(_metadata: &fs::Metadata) -> u64 {
+    // Without a link count, keep every blob rather than risk losing one
+    2
+}
@@ -0,0 +1,232 @@
+use anyhow::{Context, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, Utc};
+
+use crate::config::ViewItem;
+
+/// A `{...}` placeholder in a `date_format` or view template.
+#[derive(Debug, Clone, PartialEq)]
+enum Placeholder {
+    Year,
+    Month,
+    Month02,
+    Day,
+    Day02,
+    /// 1 to 4
+    Quarter,
+    /// English day name, e.g. `Monday`
+    Weekday,
+    Hostname,
+    /// The user running relfa
+    User,
+    Inbox,
+    Ext,
+    Mime,
+    /// Anything chrono's strftime understands, e.g. `{%G-W%V}`
+    Strftime(String),
+}
+
+impl Placeholder {
+    fn is_date(&self) -> bool {
+        matches!(
+            self,
+            Placeholder::Year
+                | Placeholder::Month
+                | Placeholder::Month02
+                | Placeholder::Day
+                | Placeholder::Day02
+                | Placeholder::Quarter
+                | Placeholder::Weekday
+                | Placeholder::Strftime(_)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+/// A parsed path template, filled in for an item and one of its timestamps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parse `template`, rejecting unknown placeholders and bad strftime
+    /// formats rather than letting them end up as literal directory names.
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .context(format!("Unclosed '{{' in template '{template}'"))?;
+            let name = &rest[start + 1..end];
+
+            let placeholder = match name {
+                "year" => Placeholder::Year,
+                "month" => Placeholder::Month,
+                "month:02" => Placeholder::Month02,
+                "day" => Placeholder::Day,
+                "day:02" => Placeholder::Day02,
+                "quarter" => Placeholder::Quarter,
+                "weekday" => Placeholder::Weekday,
+                "hostname" => Placeholder::Hostname,
+                "user" => Placeholder::User,
+                "inbox" => Placeholder::Inbox,
+                "ext" => Placeholder::Ext,
+                "mime" => Placeholder::Mime,
+                format if format.starts_with('%') => {
+                    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                        anyhow::bail!(
+                            "Invalid strftime format '{{{format}}}' in template '{template}'"
+                        );
+                    }
+                    Placeholder::Strftime(format.to_string())
+                }
+                _ => anyhow::bail!("Unknown placeholder '{{{name}}}' in template '{template}'"),
+            };
+            parts.push(Part::Placeholder(placeholder));
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+
+        Ok(Self { parts })
+    }
+
+    /// Whether anything in the template is taken from the date.
+    pub fn uses_date(&self) -> bool {
+        self.parts.iter().any(|part| match part {
+            Part::Literal(_) => false,
+            Part::Placeholder(placeholder) => placeholder.is_date(),
+        })
+    }
+
+    /// The template filled in for `item`, with date placeholders taken from `date`.
+    pub fn render(&self, item: &ViewItem, date: &DateTime<Utc>) -> String {
+        let mut rendered = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => rendered.push_str(text),
+                Part::Placeholder(placeholder) => rendered.push_str(&match placeholder {
+                    Placeholder::Year => date.year().to_string(),
+                    Placeholder::Month => date.month().to_string(),
+                    Placeholder::Month02 => format!("{:02}", date.month()),
+                    Placeholder::Day => date.day().to_string(),
+                    Placeholder::Day02 => format!("{:02}", date.day()),
+                    Placeholder::Quarter => date.month().div_ceil(3).to_string(),
+                    Placeholder::Weekday => date.format("%A").to_string(),
+                    Placeholder::Hostname => item.hostname.clone(),
+                    Placeholder::User => current_user(),
+                    Placeholder::Inbox => item.inbox().to_string(),
+                    Placeholder::Ext => item.extension(),
+                    Placeholder::Mime => item.mime_category(),
+                    Placeholder::Strftime(format) => date.format(format).to_string(),
+                }),
+            }
+        }
+        rendered
+    }
+}
+
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn item(name: &str, is_directory: bool) -> ViewItem {
+        let date = Utc.with_ymd_and_hms(2024, 2, 5, 12, 0, 0).unwrap();
+        ViewItem {
+            name: name.to_string(),
+            is_directory,
+            inbox: Some("downloads".to_string()),
+            hostname: "laptop".to_string(),
+            created: date,
+            modified: date,
+            archived: date,
+        }
+    }
+
+    fn render(template: &str, item: &ViewItem) -> String {
+        Template::parse(template)
+            .unwrap()
+            .render(item, &item.archived)
+    }
+
+    #[test]
+    fn renders_date_placeholders() {
+        let item = item("report.pdf", false);
+        assert_eq!(
+            render("{hostname}/{year}/{month:02}/{day:02}", &item),
+            "laptop/2024/02/05"
+        );
+        assert_eq!(render("{year}-{month}-{day}", &item), "2024-2-5");
+        assert_eq!(render("Q{quarter} {weekday}", &item), "Q1 Monday");
+        assert_eq!(render("{%G-W%V}", &item), "2024-W06");
+    }
+
+    #[test]
+    fn renders_item_placeholders() {
+        assert_eq!(
+            render("{inbox}/{ext}/{mime}", &item("Photo.JPG", false)),
+            "downloads/jpg/image"
+        );
+        assert_eq!(
+            render("{ext}/{mime}", &item("project", true)),
+            "folder/folder"
+        );
+        assert_eq!(render("{ext}", &item("README", false)), "other");
+    }
+
+    #[test]
+    fn keeps_literal_text() {
+        let item = item("report.pdf", false);
+        assert_eq!(render("archive", &item), "archive");
+        assert_eq!(render("by-year/{year}/", &item), "by-year/2024/");
+    }
+
+    #[test]
+    fn tells_dated_templates_apart() {
+        for template in [
+            "{year}/{month:02}",
+            "{%Y}/{%m}",
+            "by-quarter/{quarter}",
+            "{weekday}",
+            "{inbox}/{%G-W%V}",
+        ] {
+            assert!(
+                Template::parse(template).unwrap().uses_date(),
+                "{template} is dated"
+            );
+        }
+        for template in ["{hostname}/{inbox}", "{ext}/{mime}", "by-type", "{user}"] {
+            assert!(
+                !Template::parse(template).unwrap().uses_date(),
+                "{template} is not dated"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_bad_placeholders() {
+        assert!(Template::parse("{yaer}").is_err());
+        assert!(Template::parse("{year").is_err());
+        assert!(Template::parse("{%Q}").is_err());
+    }
+}
@@ -0,0 +1,891 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::archiver::Archiver;
+use crate::commands::{
+    delete_stale_item, set_item_hold, snooze_end, touch_stale_item, DEFAULT_SNOOZE,
+};
+use crate::config::Config;
+use crate::scanner::{Scanner, StaleItem};
+use crate::state::Hold;
+use crate::utils::{format_size, item_size, open_file_with_default, view_file_with_pager};
+
+/// How much of a file the preview pane reads.
+const PREVIEW_BYTES: u64 = 64 * 1024;
+
+const KEY_HINTS: &str = "↑↓ move  space mark  * mark all  a archive  n note  t touch  z snooze  p pin  d delete  v view  o open  s sort  r reverse  / filter  ? help  q quit";
+
+const HELP: &[&str] = &[
+    "Moving around",
+    "  ↑/k ↓/j       previous / next item",
+    "  PgUp PgDn     previous / next page",
+    "  Home/g End/G  first / last item",
+    "",
+    "Choosing items (actions apply to the marked items, or the current one)",
+    "  space         mark or unmark the current item",
+    "  *             mark or unmark every listed item",
+    "  /             filter by name as you type (Enter keeps it, Esc clears it)",
+    "  s             sort by age, size or name",
+    "  r             reverse the sort order",
+    "",
+    "Actions",
+    "  a             archive to the Graveyard",
+    "  n             archive with an epitaph (note)",
+    "  t             touch: keep for another period by updating the modification time",
+    "  z             snooze: leave out of scans for a while, without touching it",
+    "  p             pin: leave out of scans for good, without touching it",
+    "  d             delete permanently (asks for confirmation)",
+    "  v             view the current item with the pager",
+    "  o             open the current item with its default application",
+    "",
+    "  q/Esc         quit",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortKey {
+    Age,
+    Size,
+    Name,
+}
+
+impl SortKey {
+    fn next(self) -> Self {
+        match self {
+            SortKey::Age => SortKey::Size,
+            SortKey::Size => SortKey::Name,
+            SortKey::Name => SortKey::Age,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortKey::Age => "oldest first",
+            SortKey::Size => "largest first",
+            SortKey::Name => "name",
+        }
+    }
+}
+
+struct Entry {
+    item: StaleItem,
+    size: u64,
+    marked: bool,
+}
+
+/// What a line typed at the bottom of the screen is for.
+#[derive(Clone, Copy)]
+enum Prompt {
+    Note,
+    Snooze,
+    Delete,
+}
+
+enum Mode {
+    Browse,
+    Filter,
+    Input(Prompt, String),
+    Help,
+}
+
+/// What to do with the chosen items.
+enum Action {
+    Archive(Option<String>),
+    Touch,
+    Snooze(DateTime<Utc>),
+    Pin,
+    Delete,
+}
+
+impl Action {
+    fn past_tense(&self) -> &'static str {
+        match self {
+            Action::Archive(_) => "Archived",
+            Action::Touch => "Touched",
+            Action::Snooze(_) => "Snoozed",
+            Action::Pin => "Pinned",
+            Action::Delete => "Deleted",
+        }
+    }
+
+    /// Whether the item leaves the inbox, rather than being kept.
+    fn processes(&self) -> bool {
+        matches!(self, Action::Archive(_) | Action::Delete)
+    }
+}
+
+/// Raw mode on the alternate screen for as long as it lives.
+struct Screen;
+
+impl Screen {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self)
+    }
+
+    fn leave() {
+        let _ = execute!(io::stdout(), cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+
+    /// Hand the terminal back while `f` runs, for the pager and for the
+    /// output of actions, which stays readable after the review ends.
+    fn suspended<T>(f: impl FnOnce() -> T) -> Result<T> {
+        Self::leave();
+        let result = f();
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(result)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        Self::leave();
+    }
+}
+
+/// Full-screen review of the stale items: a sortable, filterable list with a
+/// preview pane, where several items can be marked and handled at once.
+pub fn run(config: Config) -> Result<()> {
+    let mut review = Review::new(config);
+    review.reload()?;
+
+    if review.entries.is_empty() {
+        println!("✨ No items to review - Inbox is clean!");
+        return Ok(());
+    }
+
+    let screen = Screen::enter()?;
+    let result = review.event_loop();
+    drop(screen);
+    result?;
+
+    println!("🎉 Review complete!");
+    println!(
+        "📊 Summary: {} processed, {} kept",
+        review.processed, review.kept
+    );
+    Ok(())
+}
+
+struct Review {
+    config: Config,
+    scanner: Scanner,
+    archiver: Archiver,
+    entries: Vec<Entry>,
+    /// Indices into `entries` that pass the filter, in display order
+    visible: Vec<usize>,
+    /// Position of the highlighted item in `visible`
+    cursor: usize,
+    /// Position in `visible` of the first row on screen
+    offset: usize,
+    sort: SortKey,
+    reverse: bool,
+    filter: String,
+    mode: Mode,
+    status: String,
+    preview: Option<(PathBuf, Vec<String>)>,
+    processed: usize,
+    kept: usize,
+}
+
+impl Review {
+    fn new(config: Config) -> Self {
+        Self {
+            scanner: Scanner::new(config.clone()),
+            archiver: Archiver::new(config.clone()),
+            config,
+            entries: Vec::new(),
+            visible: Vec::new(),
+            cursor: 0,
+            offset: 0,
+            sort: SortKey::Age,
+            reverse: false,
+            filter: String::new(),
+            mode: Mode::Browse,
+            status: String::new(),
+            preview: None,
+            processed: 0,
+            kept: 0,
+        }
+    }
+
+    /// Scan the inboxes again, keeping marks and the highlighted item.
+    fn reload(&mut self) -> Result<()> {
+        let marked: HashSet<PathBuf> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.marked)
+            .map(|entry| entry.item.path.clone())
+            .collect();
+        let current = self.current().map(|entry| entry.item.path.clone());
+
+        self.entries = self
+            .scanner
+            .scan_inbox()?
+            .into_iter()
+            .map(|item| Entry {
+                size: item_size(&item.path).unwrap_or(0),
+                marked: marked.contains(&item.path),
+                item,
+            })
+            .collect();
+        self.preview = None;
+        self.refresh(current);
+        Ok(())
+    }
+
+    /// Re-sort and re-filter, highlighting `current` if it is still listed.
+    fn refresh(&mut self, current: Option<PathBuf>) {
+        let sort = self.sort;
+        self.entries.sort_by(|a, b| match sort {
+            SortKey::Age => a.item.last_modified.cmp(&b.item.last_modified),
+            SortKey::Size => b.size.cmp(&a.size),
+            SortKey::Name => a.item.name.to_lowercase().cmp(&b.item.name.to_lowercase()),
+        });
+        if self.reverse {
+            self.entries.reverse();
+        }
+
+        let filter = self.filter.to_lowercase();
+        self.visible = (0..self.entries.len())
+            .filter(|&i| self.entries[i].item.name.to_lowercase().contains(&filter))
+            .collect();
+
+        self.cursor = current
+            .and_then(|path| {
+                self.visible
+                    .iter()
+                    .position(|&i| self.entries[i].item.path == path)
+            })
+            .unwrap_or(self.cursor)
+            .min(self.visible.len().saturating_sub(1));
+    }
+
+    fn current(&self) -> Option<&Entry> {
+        self.visible
+            .get(self.cursor)
+            .map(|&index| &self.entries[index])
+    }
+
+    fn current_path(&self) -> Option<PathBuf> {
+        self.current().map(|entry| entry.item.path.clone())
+    }
+
+    /// The marked items that are listed, or else the highlighted one.
+    fn targets(&self) -> Vec<StaleItem> {
+        let marked: Vec<StaleItem> = self
+            .visible
+            .iter()
+            .map(|&index| &self.entries[index])
+            .filter(|entry| entry.marked)
+            .map(|entry| entry.item.clone())
+            .collect();
+        if marked.is_empty() {
+            self.current()
+                .map(|entry| entry.item.clone())
+                .into_iter()
+                .collect()
+        } else {
+            marked
+        }
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        let last = self.visible.len().saturating_sub(1) as isize;
+        self.cursor = (self.cursor as isize + delta).clamp(0, last) as usize;
+    }
+
+    fn list_height() -> usize {
+        terminal::size()
+            .map(|(_, height)| height as usize)
+            .unwrap_or(24)
+            .saturating_sub(3)
+            .max(1)
+    }
+
+    fn event_loop(&mut self) -> Result<()> {
+        loop {
+            self.draw()?;
+            // Anything else, such as a resize, just redraws
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key)? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// React to `key`; `false` ends the review.
+    fn handle_key(&mut self, key: KeyEvent) -> Result<bool> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Ok(false);
+        }
+
+        match std::mem::replace(&mut self.mode, Mode::Browse) {
+            Mode::Browse => return self.handle_browse_key(key),
+            Mode::Help => {}
+            Mode::Filter => {
+                let current = self.current_path();
+                match key.code {
+                    KeyCode::Enter => {}
+                    KeyCode::Esc => self.filter.clear(),
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                        self.mode = Mode::Filter;
+                    }
+                    KeyCode::Char(c) => {
+                        self.filter.push(c);
+                        self.mode = Mode::Filter;
+                    }
+                    _ => self.mode = Mode::Filter,
+                }
+                self.refresh(current);
+            }
+            Mode::Input(prompt, mut input) => match key.code {
+                KeyCode::Enter => self.submit(prompt, input.trim())?,
+                KeyCode::Esc => self.status = "Cancelled".to_string(),
+                KeyCode::Backspace => {
+                    input.pop();
+                    self.mode = Mode::Input(prompt, input);
+                }
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    self.mode = Mode::Input(prompt, input);
+                }
+                _ => self.mode = Mode::Input(prompt, input),
+            },
+        }
+        Ok(true)
+    }
+
+    fn handle_browse_key(&mut self, key: KeyEvent) -> Result<bool> {
+        let page = Self::list_height() as isize;
+        self.status.clear();
+
+        match key.code {
+            KeyCode::Char('q') => return Ok(false),
+            KeyCode::Esc if self.filter.is_empty() => return Ok(false),
+            KeyCode::Esc => {
+                let current = self.current_path();
+                self.filter.clear();
+                self.refresh(current);
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-page),
+            KeyCode::PageDown => self.move_cursor(page),
+            KeyCode::Home | KeyCode::Char('g') => self.cursor = 0,
+            KeyCode::End | KeyCode::Char('G') => self.move_cursor(isize::MAX / 2),
+            KeyCode::Char(' ') => {
+                if let Some(&index) = self.visible.get(self.cursor) {
+                    self.entries[index].marked = !self.entries[index].marked;
+                    self.move_cursor(1);
+                }
+            }
+            KeyCode::Char('*') => {
+                let mark = !self.visible.iter().all(|&index| self.entries[index].marked);
+                for &index in &self.visible {
+                    self.entries[index].marked = mark;
+                }
+            }
+            KeyCode::Char('s') => {
+                let current = self.current_path();
+                self.sort = self.sort.next();
+                self.refresh(current);
+            }
+            KeyCode::Char('r') => {
+                let current = self.current_path();
+                self.reverse = !self.reverse;
+                self.refresh(current);
+            }
+            KeyCode::Char('/') => self.mode = Mode::Filter,
+            KeyCode::Char('?') => self.mode = Mode::Help,
+            KeyCode::Char('a') => self.apply(Action::Archive(None))?,
+            KeyCode::Char('n') => self.mode = Mode::Input(Prompt::Note, String::new()),
+            KeyCode::Char('t') => self.apply(Action::Touch)?,
+            KeyCode::Char('z') => self.mode = Mode::Input(Prompt::Snooze, String::new()),
+            KeyCode::Char('p') => self.apply(Action::Pin)?,
+            KeyCode::Char('d') if !self.targets().is_empty() => {
+                self.mode = Mode::Input(Prompt::Delete, String::new())
+            }
+            KeyCode::Char('v') => {
+                if let Some(path) = self.current_path() {
+                    let config = &self.config;
+                    Screen::suspended(|| view_file_with_pager(&path, config))??;
+                }
+            }
+            KeyCode::Char('o') => {
+                if let Some(path) = self.current_path() {
+                    Screen::suspended(|| open_file_with_default(&path))??;
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn submit(&mut self, prompt: Prompt, input: &str) -> Result<()> {
+        match prompt {
+            Prompt::Note => {
+                let note = Some(input.to_string()).filter(|note| !note.is_empty());
+                self.apply(Action::Archive(note))
+            }
+            Prompt::Snooze => {
+                let duration = Some(input).filter(|duration| !duration.is_empty());
+                match snooze_end(duration, None) {
+                    Ok(end) => self.apply(Action::Snooze(end)),
+                    Err(e) => {
+                        self.status = e.to_string();
+                        Ok(())
+                    }
+                }
+            }
+            Prompt::Delete => {
+                if input == self.delete_confirmation() {
+                    self.apply(Action::Delete)
+                } else {
+                    self.status = "Delete cancelled - the confirmation did not match".to_string();
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// What has to be typed to delete the chosen items: the item's exact
+    /// name, or `delete` for several.
+    fn delete_confirmation(&self) -> String {
+        match self.targets().as_slice() {
+            [item] => item.name.clone(),
+            _ => "delete".to_string(),
+        }
+    }
+
+    /// Run `action` on the chosen items through the same helpers as the
+    /// line-by-line review, then scan again.
+    fn apply(&mut self, action: Action) -> Result<()> {
+        let targets = self.targets();
+        if targets.is_empty() {
+            return Ok(());
+        }
+
+        let config = &self.config;
+        let archiver = &self.archiver;
+        let failures = Screen::suspended(|| {
+            let mut failures = Vec::new();
+            for item in &targets {
+                let result = match &action {
+                    Action::Archive(note) => archiver
+                        .archive_item_with_note(item, note.as_deref())
+                        .map(|_| ()),
+                    Action::Touch => touch_stale_item(item, config),
+                    Action::Snooze(end) => set_item_hold(
+                        config,
+                        &item.inbox,
+                        &item.name,
+                        &item.path,
+                        Some(Hold::SnoozedUntil(*end)),
+                    ),
+                    Action::Pin => set_item_hold(
+                        config,
+                        &item.inbox,
+                        &item.name,
+                        &item.path,
+                        Some(Hold::Pinned),
+                    ),
+                    Action::Delete => delete_stale_item(item, config),
+                };
+                if let Err(e) = result {
+                    eprintln!("❌ {}: {e:#}", item.name);
+                    failures.push(format!("{}: {e}", item.name));
+                }
+            }
+            failures
+        })?;
+
+        let done = targets.len() - failures.len();
+        if action.processes() {
+            self.processed += done;
+        } else {
+            self.kept += done;
+        }
+        self.status = format!(
+            "{} {done} {}",
+            action.past_tense(),
+            if done == 1 { "item" } else { "items" }
+        );
+        if let Some(failure) = failures.first() {
+            self.status
+                .push_str(&format!(", {} failed ({failure})", failures.len()));
+        }
+        if self.config.dry_run {
+            self.status
+                .push_str(" - dry run, see the output after quitting");
+        }
+
+        self.reload()
+    }
+
+    fn prompt_label(&self, prompt: Prompt) -> String {
+        match prompt {
+            Prompt::Note => "Epitaph note (empty for none):".to_string(),
+            Prompt::Snooze => {
+                format!("Snooze for how long? (e.g. 3d, 2w, 6m) [{DEFAULT_SNOOZE}]:")
+            }
+            Prompt::Delete => format!(
+                "Permanently delete {}? Type '{}' to confirm:",
+                match self.targets().len() {
+                    1 => "this item".to_string(),
+                    count => format!("{count} items"),
+                },
+                self.delete_confirmation()
+            ),
+        }
+    }
+
+    fn preview_lines(&mut self) -> &[String] {
+        let path = self.current_path();
+        if self.preview.as_ref().map(|(cached, _)| cached) != path.as_ref() {
+            self.preview = path.map(|path| {
+                let lines = preview(&path);
+                (path, lines)
+            });
+        }
+        self.preview
+            .as_ref()
+            .map(|(_, lines)| lines.as_slice())
+            .unwrap_or_default()
+    }
+
+    fn draw(&mut self) -> Result<()> {
+        let (width, height) = terminal::size()?;
+        let (width, height) = (width as usize, height as usize);
+        let list_height = Self::list_height();
+        // Only split the screen when both halves have room
+        let list_width = if width >= 80 { width / 2 } else { width };
+
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.cursor >= self.offset + list_height {
+            self.offset = self.cursor + 1 - list_height;
+        }
+
+        let mut out = io::stdout();
+        queue!(out, Clear(ClearType::All))?;
+
+        let marked = self
+            .visible
+            .iter()
+            .filter(|&&index| self.entries[index].marked)
+            .count();
+        let mut header = format!(
+            " relfa review - {} of {} items, {marked} marked, sorted {}{}",
+            self.visible.len(),
+            self.entries.len(),
+            self.sort.label(),
+            if self.reverse { " (reversed)" } else { "" }
+        );
+        if !self.filter.is_empty() {
+            header.push_str(&format!(", filter '{}'", self.filter));
+        }
+        queue!(
+            out,
+            cursor::MoveTo(0, 0),
+            SetAttribute(Attribute::Reverse),
+            Print(fit(&header, width)),
+            SetAttribute(Attribute::Reset)
+        )?;
+
+        if let Mode::Help = self.mode {
+            for (row, line) in HELP.iter().take(list_height).enumerate() {
+                queue!(
+                    out,
+                    cursor::MoveTo(0, row as u16 + 1),
+                    Print(fit(line, width))
+                )?;
+            }
+        } else {
+            self.draw_list(&mut out, list_width, list_height)?;
+            if list_width < width {
+                let preview_width = width - list_width - 1;
+                let lines: Vec<String> = self
+                    .preview_lines()
+                    .iter()
+                    .take(list_height)
+                    .map(|line| fit(line, preview_width))
+                    .collect();
+                for row in 0..list_height {
+                    queue!(
+                        out,
+                        cursor::MoveTo(list_width as u16, row as u16 + 1),
+                        Print("│"),
+                        Print(lines.get(row).map(String::as_str).unwrap_or(""))
+                    )?;
+                }
+            }
+        }
+
+        let bottom = match &self.mode {
+            Mode::Browse => KEY_HINTS.to_string(),
+            Mode::Filter => format!("/{}", self.filter),
+            Mode::Input(prompt, input) => format!("{} {input}", self.prompt_label(*prompt)),
+            Mode::Help => "Press any key to go back".to_string(),
+        };
+        queue!(
+            out,
+            cursor::MoveTo(0, height.saturating_sub(2) as u16),
+            Print(fit(&self.status, width)),
+            cursor::MoveTo(0, height.saturating_sub(1) as u16),
+            Print(fit(&bottom, width))
+        )?;
+
+        out.flush()?;
+        Ok(())
+    }
+
+    fn draw_list(&self, out: &mut impl Write, width: usize, height: usize) -> Result<()> {
+        if self.visible.is_empty() {
+            let message = if self.entries.is_empty() {
+                "  Nothing left to review - press q to quit"
+            } else {
+                "  No items match the filter"
+            };
+            queue!(out, cursor::MoveTo(0, 1), Print(fit(message, width)))?;
+            return Ok(());
+        }
+
+        let inbox_width = self
+            .entries
+            .iter()
+            .map(|entry| entry.item.inbox.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        for (row, &index) in self
+            .visible
+            .iter()
+            .skip(self.offset)
+            .take(height)
+            .enumerate()
+        {
+            let entry = &self.entries[index];
+            let details = format!(
+                "{:>5}d {:>10}  {:<inbox_width$}",
+                entry.item.age_days,
+                format_size(entry.size),
+                entry.item.inbox
+            );
+            let name = if entry.item.is_directory {
+                format!("{}/", entry.item.name)
+            } else {
+                entry.item.name.clone()
+            };
+            let name_width = width.saturating_sub(6 + details.chars().count());
+            let line = format!(
+                "{} {} {details}",
+                if entry.marked { "[x]" } else { "[ ]" },
+                fit(&name, name_width)
+            );
+
+            queue!(out, cursor::MoveTo(0, row as u16 + 1))?;
+            if self.offset + row == self.cursor {
+                queue!(out, SetAttribute(Attribute::Reverse))?;
+            }
+            queue!(
+                out,
+                Print(fit(&line, width)),
+                SetAttribute(Attribute::Reset)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// `text` cut or padded to exactly `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        format!("{text:<width$}")
+    } else if width == 0 {
+        String::new()
+    } else {
+        let mut cut: String = text.chars().take(width - 1).collect();
+        cut.push('…');
+        cut
+    }
+}
+
+/// What the preview pane shows for `path`: the start of a text file, or the
+/// contents of a folder.
+fn preview(path: &Path) -> Vec<String> {
+    if path.is_dir() {
+        return match fs::read_dir(path) {
+            Ok(entries) => {
+                let mut names: Vec<String> = entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| {
+                        let name = entry.file_name().to_string_lossy().to_string();
+                        if entry.path().is_dir() {
+                            format!("{name}/")
+                        } else {
+                            name
+                        }
+                    })
+                    .collect();
+                names.sort();
+                if names.is_empty() {
+                    vec!["(empty folder)".to_string()]
+                } else {
+                    names
+                }
+            }
+            Err(e) => vec![format!("(cannot list folder: {e})")],
+        };
+    }
+
+    let mut content = Vec::new();
+    if let Err(e) =
+        fs::File::open(path).and_then(|file| file.take(PREVIEW_BYTES).read_to_end(&mut content))
+    {
+        return vec![format!("(cannot read file: {e})")];
+    }
+    if content.contains(&0) {
+        return vec!["(binary file)".to_string()];
+    }
+
+    String::from_utf8_lossy(&content)
+        .lines()
+        .map(|line| {
+            line.replace('\t', "    ")
+                .chars()
+                .filter(|c| !c.is_control())
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RuleAction;
+    use crate::rules::ItemPolicy;
+    use crate::state::{FileIdentity, NotificationSpread};
+    use chrono::Duration;
+
+    fn entry(name: &str, age_days: i64, size: u64) -> Entry {
+        let policy = ItemPolicy {
+            age_threshold_days: 3,
+            auto_archive_threshold_days: 7,
+            auto_archive_min_notified_days: 2,
+            auto_archive_min_notified_span_days: 1,
+            action: RuleAction::Archive,
+            rule: None,
+            compress: None,
+        };
+        Entry {
+            item: StaleItem {
+                path: PathBuf::from("/inbox").join(name),
+                name: name.to_string(),
+                inbox: "default".to_string(),
+                last_modified: Utc::now() - Duration::days(age_days),
+                is_directory: false,
+                age_days,
+                notification_count: 0,
+                first_seen: None,
+                notified: NotificationSpread::default(),
+                policy,
+                identity: FileIdentity::default(),
+            },
+            size,
+            marked: false,
+        }
+    }
+
+    fn review() -> Review {
+        let mut review = Review::new(Config::default());
+        review.entries = vec![
+            entry("notes.txt", 5, 10),
+            entry("Movie.mkv", 20, 5000),
+            entry("invoice.pdf", 40, 200),
+        ];
+        review.refresh(None);
+        review
+    }
+
+    fn listed(review: &Review) -> Vec<&str> {
+        review
+            .visible
+            .iter()
+            .map(|&index| review.entries[index].item.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn sorts_by_each_key() {
+        let mut review = review();
+        assert_eq!(listed(&review), ["invoice.pdf", "Movie.mkv", "notes.txt"]);
+
+        review.sort = review.sort.next();
+        review.refresh(None);
+        assert_eq!(listed(&review), ["Movie.mkv", "invoice.pdf", "notes.txt"]);
+
+        review.sort = review.sort.next();
+        review.reverse = true;
+        review.refresh(None);
+        assert_eq!(listed(&review), ["notes.txt", "Movie.mkv", "invoice.pdf"]);
+    }
+
+    #[test]
+    fn filtering_keeps_the_highlighted_item() {
+        let mut review = review();
+        review.move_cursor(1);
+        assert_eq!(
+            review.current_path(),
+            Some(PathBuf::from("/inbox/Movie.mkv"))
+        );
+
+        review.filter = "MOV".to_string();
+        let current = review.current_path();
+        review.refresh(current);
+        assert_eq!(listed(&review), ["Movie.mkv"]);
+        assert_eq!(review.cursor, 0);
+
+        review.filter = "nothing matches".to_string();
+        review.refresh(None);
+        assert!(review.current().is_none());
+    }
+
+    #[test]
+    fn targets_marked_items_or_the_highlighted_one() {
+        let mut review = review();
+        let names = |items: Vec<StaleItem>| -> Vec<String> {
+            items.into_iter().map(|item| item.name).collect()
+        };
+        assert_eq!(names(review.targets()), ["invoice.pdf"]);
+
+        for entry in &mut review.entries {
+            entry.marked = entry.item.name != "Movie.mkv";
+        }
+        assert_eq!(names(review.targets()), ["invoice.pdf", "notes.txt"]);
+
+        // Marked items hidden by the filter are left alone
+        review.filter = "notes".to_string();
+        review.refresh(None);
+        assert_eq!(names(review.targets()), ["notes.txt"]);
+    }
+
+    #[test]
+    fn fits_text_to_the_width() {
+        assert_eq!(fit("abc", 5), "abc  ");
+        assert_eq!(fit("abcdef", 4), "abc…");
+        assert_eq!(fit("abc", 0), "");
+    }
+}
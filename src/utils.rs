@@ -1,7 +1,10 @@
 use anyhow::{Context, Result};
-use std::path::Path;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
-use crate::config::Config;
+use crate::config::{Compression, Config};
+use crate::store::ContentStore;
 
 pub fn view_file_with_pager(file_path: &Path, config: &Config) -> Result<()> {
     if !file_path.exists() {
@@ -129,3 +132,489 @@ fn copy_dir_recursive(source: &Path, dest: &Path) -> Result<()> {
 
     Ok(())
 }
+
+/// Parse a human-readable size such as `512`, `10KB`, `1.5 GB` or `2GiB`.
+/// Units are powers of 1024 regardless of the `i`.
+pub fn parse_size(size: &str) -> Result<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+
+    let number: f64 = number.parse().context(format!("Invalid size: '{size}'"))?;
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        other => return Err(anyhow::anyhow!("Unknown size unit '{other}' in '{size}'")),
+    };
+
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parse a duration such as `12h`, `3d`, `2w`, `6m` (30-day months) or `1y`.
+pub fn parse_duration(duration: &str) -> Result<chrono::Duration> {
+    let trimmed = duration.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+
+    let hours = match unit.trim().to_lowercase().as_str() {
+        "h" => 1,
+        "d" => 24,
+        "w" => 24 * 7,
+        "m" => 24 * 30,
+        "y" => 24 * 365,
+        _ => 0,
+    };
+    match number.parse::<i64>() {
+        Ok(number) if number > 0 && hours > 0 => Ok(chrono::Duration::hours(number * hours)),
+        _ => anyhow::bail!(
+            "Invalid duration '{duration}': use a number followed by h, d, w, m or y (e.g. 2w)"
+        ),
+    }
+}
+
+/// Size in bytes of a file, or of all files inside a directory.
+pub fn item_size(path: &Path) -> Result<u64> {
+    if path.is_dir() {
+        Ok(walkdir::WalkDir::new(path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| e.metadata().ok())
+            .map(|m| m.len())
+            .sum())
+    } else {
+        Ok(std::fs::metadata(path)
+            .context(format!("Failed to get metadata: {}", path.display()))?
+            .len())
+    }
+}
+
+/// SHA-256 of a file, or of the relative paths and contents of everything in a directory.
+/// Other symlinks inside a directory are hashed by their target path.
+pub fn item_hash(path: &Path) -> Result<String> {
+    use sha2::{Digest, Sha256};
+
+    fn hash_file(file: &Path, hasher: &mut Sha256) -> Result<()> {
+        let mut reader = std::fs::File::open(file)
+            .context(format!("Failed to open for hashing: {}", file.display()))?;
+        std::io::copy(&mut reader, hasher)
+            .context(format!("Failed to hash: {}", file.display()))?;
+        Ok(())
+    }
+
+    let mut hasher = Sha256::new();
+
+    if path.is_dir() {
+        for entry in walkdir::WalkDir::new(path).sort_by_file_name() {
+            let entry = entry?;
+            let relative = entry.path().strip_prefix(path).unwrap_or(entry.path());
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update([0]);
+            if entry.path_is_symlink() && ContentStore::is_blob(entry.path()) {
+                // Deduplicated files count as their content, like hardlinked ones
+                hash_file(entry.path(), &mut hasher)?;
+            } else if entry.path_is_symlink() {
+                hasher.update(
+                    std::fs::read_link(entry.path())?
+                        .to_string_lossy()
+                        .as_bytes(),
+                );
+            } else if entry.file_type().is_file() {
+                hash_file(entry.path(), &mut hasher)?;
+            }
+            hasher.update([0]);
+        }
+    } else {
+        hash_file(path, &mut hasher)?;
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Replace `path` with `content` through a temporary file and a rename, so
+/// other processes never read a half-written file.
+pub fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .context(format!("Invalid file path: {}", path.display()))?
+        .to_string_lossy();
+    let temp_path = path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+
+    let result = fs::File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(content.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result.context(format!("Failed to write {}", path.display()))
+}
+
+/// Move a file or folder, falling back to copy + remove across filesystems.
+pub fn move_item(src: &Path, dst: &Path) -> Result<()> {
+    // Try rename first (faster for same filesystem)
+    match fs::rename(src, dst) {
+        Ok(()) => Ok(()),
+        Err(e) if e.raw_os_error() == Some(18) => {
+            // Error 18 is "Invalid cross-device link" - use copy + remove instead
+            if src.is_dir() {
+                copy_dir_all(src, dst)?;
+            } else {
+                fs::copy(src, dst).context("Failed to copy file across devices")?;
+            }
+            copy_times(src, dst)?;
+
+            // Only give up the source once the copy is known to be identical
+            if item_hash(src)? != item_hash(dst)? {
+                delete_item(dst).ok();
+                anyhow::bail!(
+                    "Copy of {} to {} does not match the original; the original was kept",
+                    src.display(),
+                    dst.display()
+                );
+            }
+
+            if src.is_dir() {
+                remove_dir_with_permissions(src)
+                    .context("Failed to remove source directory after copy")?;
+            } else {
+                fs::remove_file(src).context("Failed to remove source file after copy")?;
+            }
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn remove_dir_with_permissions(path: &Path) -> Result<()> {
+    // First try normal removal
+    match fs::remove_dir_all(path) {
+        Ok(()) => Ok(()),
+        Err(_) => {
+            // If that fails, try to fix permissions recursively and then remove
+            fix_permissions_recursive(path)?;
+            fs::remove_dir_all(path)
+                .context("Failed to remove directory even after fixing permissions")
+        }
+    }
+}
+
+fn fix_permissions_recursive(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Make the directory writable
+        if path.is_dir() {
+            let mut perms = fs::metadata(path)?.permissions();
+            perms.set_mode(perms.mode() | 0o700); // Add owner write/execute permissions
+            fs::set_permissions(path, perms)?;
+
+            // Recursively fix permissions for contents
+            if let Ok(entries) = fs::read_dir(path) {
+                for entry in entries.flatten() {
+                    let entry_path = entry.path();
+                    if entry_path.is_dir() {
+                        fix_permissions_recursive(&entry_path)?;
+                    } else {
+                        // Make files writable
+                        if let Ok(metadata) = fs::metadata(&entry_path) {
+                            let mut perms = metadata.permissions();
+                            perms.set_mode(perms.mode() | 0o600); // Add owner read/write permissions
+                            let _ = fs::set_permissions(&entry_path, perms);
+                            // Ignore errors for broken symlinks
+                        }
+                    }
+                }
+            }
+        }
+    }
+    #[cfg(windows)]
+    {
+        // On Windows, try to remove read-only attribute
+        let _ = fs::metadata(path).and_then(|metadata| {
+            let mut perms = metadata.permissions();
+            perms.set_readonly(false);
+            fs::set_permissions(path, perms)
+        });
+    }
+    Ok(())
+}
+
+/// Remove the date directories left empty above `path`, up to `root`.
+pub fn remove_empty_parents(path: &Path, root: &Path) {
+    let mut current = path.parent();
+    while let Some(dir) = current {
+        if dir == root || !dir.starts_with(root) {
+            break;
+        }
+        // Fails (and stops) as soon as a directory still has entries
+        if fs::remove_dir(dir).is_err() {
+            break;
+        }
+        current = dir.parent();
+    }
+}
+
+/// `name` with `_<counter>` added before its extension.
+pub fn numbered_name(name: &str, counter: usize) -> String {
+    // Keep the extensions of bundles, such as `.tar.zst`, together
+    let bundle = [Compression::Gzip, Compression::Zstd]
+        .into_iter()
+        .map(|compression| format!(".{}", compression.extension()))
+        .find(|extension| name.len() > extension.len() && name.ends_with(extension.as_str()));
+    let dot_pos = match bundle {
+        Some(extension) => Some(name.len() - extension.len()),
+        None => name.rfind('.'),
+    };
+    match dot_pos {
+        Some(dot_pos) => format!("{}_{}{}", &name[..dot_pos], counter, &name[dot_pos..]),
+        None => format!("{name}_{counter}"),
+    }
+}
+
+pub fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let ty = entry.file_type()?;
+        let src_path = entry.path();
+        let dst_path = dst.join(entry.file_name());
+
+        if ty.is_dir() {
+            copy_dir_all(&src_path, &dst_path)?;
+        } else if ty.is_symlink() {
+            copy_symlink(&src_path, &dst_path)?;
+        } else {
+            fs::copy(&src_path, &dst_path).context("Failed to copy file")?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy the symlink at `src` itself to `dst`, without following it.
+pub fn copy_symlink(src: &Path, dst: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        if let Ok(target) = fs::read_link(src) {
+            std::os::unix::fs::symlink(&target, dst).context("Failed to copy symlink")?;
+        }
+    }
+    #[cfg(windows)]
+    {
+        // On Windows, try to copy as file/dir symlink based on target
+        if let Ok(target) = fs::read_link(src) {
+            if target.is_dir() {
+                std::os::windows::fs::symlink_dir(&target, dst)
+                    .context("Failed to copy directory symlink")?;
+            } else {
+                std::os::windows::fs::symlink_file(&target, dst)
+                    .context("Failed to copy file symlink")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Give `dst` (and everything inside it) the access and modification times of `src`.
+pub fn copy_times(src: &Path, dst: &Path) -> Result<()> {
+    // Children first, so setting a directory's time is not undone by writing into it
+    if src.is_dir() && !src.is_symlink() {
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            let dst_path = dst.join(entry.file_name());
+            if dst_path.symlink_metadata().is_ok() && !entry.file_type()?.is_symlink() {
+                copy_times(&entry.path(), &dst_path)?;
+            }
+        }
+    }
+
+    let metadata = fs::metadata(src)?;
+    filetime::set_file_times(
+        dst,
+        filetime::FileTime::from_last_access_time(&metadata),
+        filetime::FileTime::from_last_modification_time(&metadata),
+    )
+    .context(format!("Failed to set timestamps for: {}", dst.display()))
+}
+
+/// Create `link` pointing at `target` through a relative path, so the Graveyard
+/// can be moved or mounted elsewhere.
+pub fn create_relative_symlink(target: &Path, link: &Path) -> Result<()> {
+    // Calculate relative path from link to target to avoid cross-device issues
+    let relative_target = relative_path(link.parent().unwrap(), target)?;
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(&relative_target, link).context("Failed to create symlink")?;
+    }
+
+    #[cfg(windows)]
+    {
+        if target.is_dir() {
+            std::os::windows::fs::symlink_dir(&relative_target, link)
+                .context("Failed to create directory symlink")?;
+        } else {
+            std::os::windows::fs::symlink_file(&relative_target, link)
+                .context("Failed to create file symlink")?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn relative_path(from_dir: &Path, to_path: &Path) -> Result<PathBuf> {
+    let from_abs = from_dir
+        .canonicalize()
+        .unwrap_or_else(|_| from_dir.to_path_buf());
+    // Keep a symlinked target as the link itself rather than what it points to
+    let to_abs = canonical_location(to_path);
+
+    // Find common ancestor
+    let from_components: Vec<_> = from_abs.components().collect();
+    let to_components: Vec<_> = to_abs.components().collect();
+
+    let common_len = from_components
+        .iter()
+        .zip(to_components.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    // Build relative path
+    let mut relative_path = PathBuf::new();
+
+    // Add ".." for each directory we need to go up from the common ancestor
+    for _ in common_len..from_components.len() {
+        relative_path.push("..");
+    }
+
+    // Add the path components from common ancestor to target
+    for component in &to_components[common_len..] {
+        relative_path.push(component.as_os_str());
+    }
+
+    // Handle the case where we're in the same directory
+    if relative_path.as_os_str().is_empty() {
+        relative_path.push(to_path.file_name().unwrap());
+    }
+
+    Ok(relative_path)
+}
+
+/// Where the symlink at `link` points, resolved against its directory but not
+/// followed any further (so dangling links resolve too).
+pub fn symlink_target(link: &Path) -> Option<PathBuf> {
+    let target = fs::read_link(link).ok()?;
+    let joined = match link.parent() {
+        Some(parent) if target.is_relative() => parent.join(target),
+        _ => target,
+    };
+
+    // Lexically drop `.` and `..` components
+    let mut resolved = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other.as_os_str()),
+        }
+    }
+    Some(resolved)
+}
+
+/// `path` with its directory canonicalized but the last component kept as
+/// is, so symlinks compare by location rather than by what they point to.
+pub fn canonical_location(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent
+            .canonicalize()
+            .map(|parent| parent.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Human-readable size with 1024-based units, the inverse of `parse_size`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_names_before_their_extension() {
+        assert_eq!(numbered_name("report.pdf", 1), "report_1.pdf");
+        assert_eq!(numbered_name("archive.tar.gz", 2), "archive_2.tar.gz");
+        assert_eq!(numbered_name("project.tar.zst", 3), "project_3.tar.zst");
+        assert_eq!(numbered_name("README", 1), "README_1");
+    }
+
+    #[test]
+    fn only_keeps_trailing_bundle_extensions_together() {
+        assert_eq!(numbered_name("my.tar.notes.txt", 2), "my.tar.notes_2.txt");
+        assert_eq!(numbered_name("backup.tar", 1), "backup_1.tar");
+        assert_eq!(numbered_name("old.tar.gz.bak", 1), "old.tar.gz_1.bak");
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("10b").unwrap(), 10);
+        assert_eq!(parse_size("2k").unwrap(), 2048);
+        assert_eq!(parse_size("1.5 MB").unwrap(), 1536 * 1024);
+        assert_eq!(parse_size("3GiB").unwrap(), 3 << 30);
+        assert_eq!(parse_size("1t").unwrap(), 1 << 40);
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        for size in ["", "mb", "10 parsecs", "1..5k"] {
+            assert!(parse_size(size).is_err(), "{size} should be rejected");
+        }
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("12h").unwrap(), chrono::Duration::hours(12));
+        assert_eq!(parse_duration("3d").unwrap(), chrono::Duration::days(3));
+        assert_eq!(parse_duration(" 2W ").unwrap(), chrono::Duration::weeks(2));
+        assert_eq!(parse_duration("6m").unwrap(), chrono::Duration::days(180));
+        assert_eq!(parse_duration("1y").unwrap(), chrono::Duration::days(365));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for duration in ["", "3", "d", "0d", "-1d", "1.5d", "2 weeks"] {
+            assert!(
+                parse_duration(duration).is_err(),
+                "{duration} should be rejected"
+            );
+        }
+    }
+}